@@ -1,7 +1,15 @@
-use aoc2019::read_input;
+use aoc2019::fuel::total_fuel;
+use aoc2019::read_input_or_panic;
 
 fn calculate_fuel(weights: Vec<i64>) -> i64 {
-    weights.iter().fold(0, |acc, n| acc + (n / 3) - 2)
+    total_fuel(weights, false)
+}
+
+fn main() {
+    println!(
+        "Fuel needed: {}",
+        calculate_fuel(read_input_or_panic("assets/day1_input"))
+    );
 }
 
 #[cfg(test)]
@@ -14,10 +22,3 @@ mod tests {
         assert_eq!(calculate_fuel(input), 2 + 2 + 654 + 33583);
     }
 }
-
-fn main() {
-    println!(
-        "Fuel needed: {}",
-        calculate_fuel(read_input("assets/day1_input"))
-    );
-}
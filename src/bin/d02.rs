@@ -1,20 +1,70 @@
 use aoc2019::read_csv_ints;
 use aoc2019::vm::VM;
 
-fn main() {
-    let mut program = read_csv_ints("assets/day2_input");
-    let desired_result = 19690720;
+// Restores the "1202 program alarm" state and returns what ends up at
+// address 0.
+fn day2_part1(program: &[i64]) -> i64 {
+    let mut vm = VM::new(program.to_vec());
+    vm.write_mem(1, 12);
+    vm.write_mem(2, 2);
+    vm.run().expect("day 2 program failed");
+    vm.read_mem(0)
+}
 
+// Searches for the noun and verb that produce desired_result and returns
+// 100 * noun + verb.
+fn day2_part2(program: &[i64], desired_result: i64) -> Option<i64> {
+    // A single VM is reset for every attempt to avoid reallocating memory.
+    let mut vm = VM::new(program.to_vec());
     for noun in 0..100 {
         for verb in 0..100 {
-            program[1] = noun;
-            program[2] = verb;
-            let mut vm = VM::new(program.clone());
-            vm.run();
-            if vm.bytecode()[0] == desired_result {
-                println!("100 * {} + {} = {}", noun, verb, 100 * noun + verb);
-                return;
+            vm.reset_to(program);
+            vm.write_mem(1, noun);
+            vm.write_mem(2, verb);
+            // Some noun and verb pairs make the program jump into garbage.
+            if vm.run().is_ok() && vm.read_mem(0) == desired_result {
+                return Some(100 * noun + verb);
             }
         }
     }
+
+    None
+}
+
+fn main() {
+    let program = read_csv_ints("assets/day2_input");
+    println!("part 1: {}", day2_part1(&program));
+    match day2_part2(&program, 19690720) {
+        Some(v) => println!("part 2: {}", v),
+        None => println!("part 2: no noun and verb found"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use aoc2019::vm::assert_no_output;
+
+    #[test]
+    fn test_day2_part1() {
+        // The sample with 12 and 2 poked in adds the 0 past the end of
+        // memory to the 2 at address 2, then multiplies that by 50.
+        let program = [1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+        assert_eq!(day2_part1(&program), 100);
+
+        // Part 1 is the search's value for noun 12 and verb 2.
+        let program = read_csv_ints("assets/day2_input");
+        let answer = day2_part1(&program);
+        assert_eq!(day2_part2(&program, answer), Some(1202));
+    }
+
+    #[test]
+    fn test_program_has_no_output() {
+        // The answer lives in memory so any output means an instruction
+        // was decoded incorrectly.
+        let mut program = read_csv_ints("assets/day2_input");
+        program[1] = 12;
+        program[2] = 2;
+        assert_no_output(&program, &[]);
+    }
 }
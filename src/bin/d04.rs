@@ -1,27 +1,95 @@
 use std::collections::HashMap;
+use std::ops::Range;
+#[cfg(feature = "parallel")]
+use std::thread;
 
-fn count_valid_passwords() -> i32 {
-    let mut count = 0;
-    for n in 264793..803936 {
-        let digits: Vec<u32> = n
-            .to_string()
-            .chars()
-            .map(|c| c.to_digit(10).unwrap())
-            .collect();
+// The puzzle input.
+const PUZZLE_RANGE: Range<u32> = 264793..803936;
+
+#[derive(Debug, Copy, Clone)]
+enum Rule {
+    // Two adjacent digits are the same.
+    Part1,
+    // Some digit appears exactly twice in a row.
+    Part2,
+}
+
+fn is_valid_password(n: u32, rule: Rule) -> bool {
+    let digits: Vec<u32> = n
+        .to_string()
+        .chars()
+        .map(|c| c.to_digit(10).unwrap())
+        .collect();
+
+    if !consecutive_duplicates(&digits) || !non_decreasing(&digits) {
+        return false;
+    }
+
+    match rule {
+        Rule::Part1 => true,
+        Rule::Part2 => atleast_one_digit_twice(&digits),
+    }
+}
 
-        if consecutive_duplicates(&digits)
-            && non_decreasing(&digits)
-            && atleast_one_digit_twice(&digits)
-        {
-            count += 1;
+// Counts the passwords valid under each rule in a single pass, returning
+// (part 1, part 2). Every part 2 password is also a part 1 password, so the
+// part 2 rule is only checked for those.
+fn count_both_parts(range: Range<u32>) -> (u32, u32) {
+    let mut part1 = 0;
+    let mut part2 = 0;
+    for n in range {
+        if is_valid_password(n, Rule::Part1) {
+            part1 += 1;
+            if is_valid_password(n, Rule::Part2) {
+                part2 += 1;
+            }
         }
     }
 
-    count
+    (part1, part2)
+}
+
+// Same as count_both_parts but splits the range into one chunk per CPU and
+// counts them on separate threads.
+#[cfg(feature = "parallel")]
+fn count_both_parts_parallel(range: Range<u32>) -> (u32, u32) {
+    let threads = thread::available_parallelism().map_or(1, |n| n.get()) as u32;
+    let chunk = ((range.end.saturating_sub(range.start)) / threads).max(1);
+
+    thread::scope(|s| {
+        let handles: Vec<_> = (0..threads)
+            .map(|i| {
+                let start = (range.start + i * chunk).min(range.end);
+                let end = if i + 1 == threads {
+                    range.end
+                } else {
+                    (start + chunk).min(range.end)
+                };
+                s.spawn(move || count_both_parts(start..end))
+            })
+            .collect();
+
+        handles
+            .into_iter()
+            .map(|h| h.join().unwrap())
+            .fold((0, 0), |(a1, a2), (b1, b2)| (a1 + b1, a2 + b2))
+    })
+}
+
+// (part 1, part 2) for the puzzle range.
+#[cfg(not(feature = "parallel"))]
+fn count_valid_passwords() -> (u32, u32) {
+    count_both_parts(PUZZLE_RANGE)
+}
+
+// (part 1, part 2) for the puzzle range, using one thread per CPU.
+#[cfg(feature = "parallel")]
+fn count_valid_passwords() -> (u32, u32) {
+    count_both_parts_parallel(PUZZLE_RANGE)
 }
 
 // part 2 condition
-fn atleast_one_digit_twice(digits: &Vec<u32>) -> bool {
+fn atleast_one_digit_twice(digits: &[u32]) -> bool {
     let mut m = HashMap::new();
     for n in digits {
         let count = m.entry(n).or_insert(0);
@@ -37,7 +105,7 @@ fn atleast_one_digit_twice(digits: &Vec<u32>) -> bool {
     false
 }
 
-fn consecutive_duplicates(digits: &Vec<u32>) -> bool {
+fn consecutive_duplicates(digits: &[u32]) -> bool {
     for i in 0..digits.len() - 1 {
         if digits[i] == digits[i + 1] {
             return true;
@@ -47,7 +115,7 @@ fn consecutive_duplicates(digits: &Vec<u32>) -> bool {
     false
 }
 
-fn non_decreasing(digits: &Vec<u32>) -> bool {
+fn non_decreasing(digits: &[u32]) -> bool {
     for i in 0..digits.len() - 1 {
         if digits[i] > digits[i + 1] {
             return false;
@@ -57,17 +125,45 @@ fn non_decreasing(digits: &Vec<u32>) -> bool {
     true
 }
 
+fn main() {
+    let (part1, part2) = count_valid_passwords();
+    println!("part 1: {}", part1);
+    println!("part 2: {}", part2);
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
     fn test_count_valid_passwords() {
-        assert_eq!(count_valid_passwords(), 628);
+        assert_eq!(count_valid_passwords(), (966, 628));
     }
-}
 
-fn main() {
-    let count = count_valid_passwords();
-    println!("Count: {}", count);
+    #[test]
+    #[cfg(feature = "parallel")]
+    fn test_count_both_parts_parallel() {
+        assert_eq!(
+            count_both_parts_parallel(PUZZLE_RANGE),
+            count_both_parts(PUZZLE_RANGE)
+        );
+        assert_eq!(count_both_parts_parallel(PUZZLE_RANGE), (966, 628));
+        assert_eq!(count_both_parts_parallel(5..5), (0, 0));
+    }
+
+    #[test]
+    fn test_count_both_parts() {
+        assert_eq!(count_both_parts(PUZZLE_RANGE), (966, 628));
+    }
+
+    #[test]
+    fn test_is_valid_password_examples() {
+        assert!(is_valid_password(111111, Rule::Part1));
+        assert!(!is_valid_password(223450, Rule::Part1));
+        assert!(!is_valid_password(123789, Rule::Part1));
+
+        assert!(is_valid_password(112233, Rule::Part2));
+        assert!(!is_valid_password(123444, Rule::Part2));
+        assert!(is_valid_password(111122, Rule::Part2));
+    }
 }
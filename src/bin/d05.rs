@@ -1,11 +1,75 @@
-use aoc2019::read_csv_ints;
 use aoc2019::vm::VM;
+use aoc2019::{read_csv_ints, read_csv_ints_from};
+use std::env;
+use std::io;
 
+// Runs the diagnostic program for the given system ID and returns all of
+// its outputs. Part 1 uses system ID 1 and part 2 uses 5.
+fn run_diagnostic(program: &[i64], system_id: i64) -> Vec<i64> {
+    let mut vm = VM::new(program.to_vec()).with_input(system_id);
+    vm.run().expect("diagnostic program failed");
+    vm.outputs()
+}
+
+// usage: d05 [system id] [-]
+//
+// The program is read from stdin instead of the puzzle input when "-" is
+// given.
 fn main() {
-    let program = read_csv_ints("assets/day5_input");
-    println!("{:?}", program);
-    let mut vm = VM::new(program);
-    vm.set_inputs(&[5]);
-    vm.run();
-    println!("{:?}", vm.outputs());
+    let mut system_id = 1;
+    let mut from_stdin = false;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "-" => from_stdin = true,
+            v => system_id = v.parse::<i64>().expect("system ID must be an integer"),
+        }
+    }
+
+    let program = if from_stdin {
+        match read_csv_ints_from(io::stdin().lock()) {
+            Ok(p) => p,
+            Err(e) => panic!("stdin: {}", e),
+        }
+    } else {
+        read_csv_ints("assets/day5_input")
+    };
+    println!("{:?}", run_diagnostic(&program, system_id));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comparisons() {
+        // Outputs 1 if the input is equal to 8 (position mode).
+        let program = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+        assert_eq!(run_diagnostic(&program, 8), vec![1]);
+        assert_eq!(run_diagnostic(&program, 7), vec![0]);
+
+        // Outputs 1 if the input is less than 8 (immediate mode).
+        let program = vec![3, 3, 1107, -1, 8, 3, 4, 3, 99];
+        assert_eq!(run_diagnostic(&program, 7), vec![1]);
+        assert_eq!(run_diagnostic(&program, 8), vec![0]);
+    }
+
+    #[test]
+    fn test_jumps() {
+        // Outputs 999 below 8, 1000 at 8 and 1001 above 8.
+        let program = vec![
+            3, 21, 1008, 21, 8, 20, 1005, 20, 22, 107, 8, 21, 20, 1006, 20, 31, 1106, 0, 36, 98, 0,
+            0, 1002, 21, 125, 20, 4, 20, 1105, 1, 46, 104, 999, 1105, 1, 46, 1101, 1000, 1, 20, 4,
+            20, 1105, 1, 46, 98, 99,
+        ];
+        assert_eq!(run_diagnostic(&program, 7), vec![999]);
+        assert_eq!(run_diagnostic(&program, 8), vec![1000]);
+        assert_eq!(run_diagnostic(&program, 9), vec![1001]);
+    }
+
+    #[test]
+    fn test_day5() {
+        let program = read_csv_ints("assets/day5_input");
+        let outputs = run_diagnostic(&program, 1);
+        assert!(outputs[..outputs.len() - 1].iter().all(|&v| v == 0));
+    }
 }
@@ -1,29 +1,24 @@
 use aoc2019::read_csv_ints;
-use aoc2019::vm::VM;
+use aoc2019::vm::{try_chain_output, PhaseError};
 
 use itertools::Itertools;
 
-fn calculate_thruster_output(program: Vec<i64>, inputs: &[i64]) -> i64 {
-    let mut vms = vec![];
-
-    for i in 0..5 {
-        vms.push(VM::new(program.clone()));
-        // Set the phase value.
-        vms[i].set_inputs(&[inputs[i]]);
-    }
-
-    for i in 0..5 {
-        let mut signal = 0;
+fn calculate_thruster_output(program: Vec<i64>, inputs: &[i64]) -> Result<i64, PhaseError> {
+    try_chain_output(&program, inputs, 0..5)
+}
 
-        if i > 0 {
-            signal = vms[i - 1].get_last_output();
+fn main() {
+    let program = read_csv_ints("assets/day7_input");
+    let perms = (0..5).permutations(5);
+    let mut max_thrust = 0;
+    for x in perms {
+        let thrust = calculate_thruster_output(program.clone(), &x).expect("amplifiers failed");
+        if thrust > max_thrust {
+            max_thrust = thrust;
         }
-
-        vms[i].set_inputs(&[signal]);
-        vms[i].run();
     }
 
-    vms[4].get_last_output()
+    println!("Max thrust: {}", max_thrust);
 }
 
 #[cfg(test)]
@@ -38,7 +33,7 @@ mod tests {
 
         let inputs = [4, 3, 2, 1, 0];
 
-        assert_eq!(43210, calculate_thruster_output(program, &inputs));
+        assert_eq!(Ok(43210), calculate_thruster_output(program, &inputs));
     }
 
     #[test]
@@ -50,7 +45,7 @@ mod tests {
 
         let inputs = [0, 1, 2, 3, 4];
 
-        assert_eq!(54321, calculate_thruster_output(program, &inputs));
+        assert_eq!(Ok(54321), calculate_thruster_output(program, &inputs));
     }
 
     #[test]
@@ -62,20 +57,17 @@ mod tests {
 
         let inputs = [1, 0, 4, 3, 2];
 
-        assert_eq!(65210, calculate_thruster_output(program, &inputs));
+        assert_eq!(Ok(65210), calculate_thruster_output(program, &inputs));
     }
-}
 
-fn main() {
-    let program = read_csv_ints("assets/day7_input");
-    let perms = (0..5).permutations(5);
-    let mut max_thrust = 0;
-    for x in perms {
-        let thrust = calculate_thruster_output(program.clone(), &x);
-        if thrust > max_thrust {
-            max_thrust = thrust;
-        }
+    #[test]
+    fn test_duplicate_phases() {
+        let program = vec![
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+        assert_eq!(
+            calculate_thruster_output(program, &[0, 0, 1, 2, 3]),
+            Err(PhaseError::Duplicate(0))
+        );
     }
-
-    println!("Max thrust: {}", max_thrust);
 }
@@ -0,0 +1,46 @@
+use aoc2019::vm::{disassemble, disassemble_annotated};
+use aoc2019::{read_csv_ints_from, read_csv_ints_strict};
+use std::env;
+use std::io;
+use std::process;
+
+// Prints the disassembly of an Intcode program.
+//
+// usage: disasm [--annotate] [path]
+//
+// The program is read from stdin when no path (or "-") is given.
+fn main() {
+    let mut annotate = false;
+    let mut path = None;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--annotate" => annotate = true,
+            _ if path.is_none() => path = Some(arg),
+            _ => {
+                eprintln!("usage: disasm [--annotate] [path]");
+                process::exit(2);
+            }
+        }
+    }
+
+    let program = match path.as_deref() {
+        None | Some("-") => read_csv_ints_from(io::stdin().lock()),
+        Some(p) => read_csv_ints_strict(p),
+    };
+    let program = match program {
+        Ok(p) => p,
+        Err(e) => {
+            eprintln!("{}: {}", path.as_deref().unwrap_or("stdin"), e);
+            process::exit(1);
+        }
+    };
+
+    let lines = if annotate {
+        disassemble_annotated(&program)
+    } else {
+        disassemble(&program)
+    };
+    for l in lines {
+        println!("{}", l);
+    }
+}
@@ -0,0 +1,138 @@
+use std::error::Error;
+use std::fmt;
+
+use crate::geometry::PathError;
+use crate::image::ImageError;
+use crate::vm::VmError;
+use crate::InputError;
+
+#[derive(Debug, PartialEq)]
+pub enum OrbitError {
+    // A line that isn't of the form "A)B".
+    MalformedEntry(String),
+    // A body that doesn't appear in the orbit map.
+    UnknownBody(String),
+    // The two bodies don't orbit a common body.
+    Disconnected(String, String),
+}
+
+impl fmt::Display for OrbitError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OrbitError::MalformedEntry(s) => write!(f, "malformed orbit entry: {:?}", s),
+            OrbitError::UnknownBody(s) => write!(f, "unknown body: {}", s),
+            OrbitError::Disconnected(a, b) => write!(f, "{} and {} aren't connected", a, b),
+        }
+    }
+}
+
+impl Error for OrbitError {}
+
+// Umbrella error for everything the library can fail with.
+#[derive(Debug)]
+pub enum AocError {
+    Input(InputError),
+    Vm(VmError),
+    Path(PathError),
+    Orbit(OrbitError),
+    Image(ImageError),
+}
+
+impl fmt::Display for AocError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AocError::Input(e) => write!(f, "input error: {}", e),
+            AocError::Vm(e) => write!(f, "vm error: {}", e),
+            AocError::Path(e) => write!(f, "path error: {}", e),
+            AocError::Orbit(e) => write!(f, "orbit error: {}", e),
+            AocError::Image(e) => write!(f, "image error: {}", e),
+        }
+    }
+}
+
+impl Error for AocError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AocError::Input(e) => Some(e),
+            AocError::Vm(e) => Some(e),
+            AocError::Path(e) => Some(e),
+            AocError::Orbit(e) => Some(e),
+            AocError::Image(e) => Some(e),
+        }
+    }
+}
+
+impl From<InputError> for AocError {
+    fn from(e: InputError) -> Self {
+        AocError::Input(e)
+    }
+}
+
+impl From<VmError> for AocError {
+    fn from(e: VmError) -> Self {
+        AocError::Vm(e)
+    }
+}
+
+impl From<PathError> for AocError {
+    fn from(e: PathError) -> Self {
+        AocError::Path(e)
+    }
+}
+
+impl From<OrbitError> for AocError {
+    fn from(e: OrbitError) -> Self {
+        AocError::Orbit(e)
+    }
+}
+
+impl From<ImageError> for AocError {
+    fn from(e: ImageError) -> Self {
+        AocError::Image(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_conversions() {
+        let e: AocError = InputError::Parse {
+            line: 3,
+            text: "x".to_owned(),
+        }
+        .into();
+        assert!(matches!(
+            e,
+            AocError::Input(InputError::Parse { line: 3, .. })
+        ));
+
+        let e: AocError = VmError::WriteLimitExceeded.into();
+        assert!(matches!(e, AocError::Vm(VmError::WriteLimitExceeded)));
+
+        let e: AocError = PathError::BadToken {
+            index: 2,
+            token: "X5".to_owned(),
+        }
+        .into();
+        assert!(matches!(
+            e,
+            AocError::Path(PathError::BadToken { index: 2, .. })
+        ));
+
+        let e: AocError = OrbitError::UnknownBody("YOU".to_owned()).into();
+        assert!(matches!(e, AocError::Orbit(OrbitError::UnknownBody(_))));
+        assert_eq!(e.to_string(), "orbit error: unknown body: YOU");
+
+        let e: AocError = ImageError::EmptyLayer.into();
+        assert!(matches!(e, AocError::Image(ImageError::EmptyLayer)));
+
+        // Works with ? into a boxed error.
+        fn fails() -> Result<(), Box<dyn Error>> {
+            Err(AocError::from(VmError::WriteLimitExceeded))?;
+            Ok(())
+        }
+        assert!(fails().is_err());
+    }
+}
@@ -0,0 +1,46 @@
+// Fuel calculations for day 1.
+
+fn fuel(mass: i64) -> i64 {
+    (mass / 3) - 2
+}
+
+// Fuel for a module when the fuel itself also needs fuel (part 2).
+fn recursive_fuel(mass: i64) -> i64 {
+    let mut result = 0;
+    let mut tmp = fuel(mass);
+    while tmp > 0 {
+        result += tmp;
+        tmp = fuel(tmp);
+    }
+    result
+}
+
+// Total fuel for all the masses. Part 1 uses recursive = false and part 2
+// uses recursive = true.
+pub fn total_fuel(masses: impl IntoIterator<Item = i64>, recursive: bool) -> i64 {
+    masses
+        .into_iter()
+        .map(|m| {
+            if recursive {
+                recursive_fuel(m)
+            } else {
+                fuel(m)
+            }
+        })
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_total_fuel() {
+        assert_eq!(
+            total_fuel(vec![12, 14, 1969, 100756], false),
+            2 + 2 + 654 + 33583
+        );
+        assert_eq!(total_fuel(vec![14, 1969, 100756], true), 2 + 966 + 50346);
+        assert_eq!(total_fuel(Vec::new(), true), 0);
+    }
+}
@@ -0,0 +1,930 @@
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use std::ops::{Add, Index, IndexMut};
+
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Point {
+    pub x: i64,
+    pub y: i64,
+}
+
+impl Point {
+    pub fn new(x: i64, y: i64) -> Point {
+        Point { x, y }
+    }
+
+    // Manhattan distance. The math is done in i128 so that points near the
+    // edges of the i64 range don't overflow; the result saturates at i64::MAX.
+    pub fn distance(&self, p: &Point) -> i64 {
+        let dx = (i128::from(self.x) - i128::from(p.x)).abs();
+        let dy = (i128::from(self.y) - i128::from(p.y)).abs();
+        i64::try_from(dx + dy).unwrap_or(i64::MAX)
+    }
+}
+
+// A point with D integer coordinates, e.g. PointN<3> for positions in space.
+// Point stays the 2D type used by the wire puzzles.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct PointN<const D: usize>(pub [i64; D]);
+
+impl<const D: usize> PointN<D> {
+    pub fn new(coords: [i64; D]) -> PointN<D> {
+        PointN(coords)
+    }
+
+    // Sum of the absolute values of the coordinates, saturating at i64::MAX
+    // like Point::distance.
+    pub fn manhattan(&self) -> i64 {
+        saturating_sum(self.0.iter().map(|c| c.unsigned_abs()))
+    }
+
+    // Manhattan distance, saturating at i64::MAX.
+    pub fn distance(&self, p: &PointN<D>) -> i64 {
+        saturating_sum(self.0.iter().zip(p.0.iter()).map(|(a, b)| a.abs_diff(*b)))
+    }
+}
+
+fn saturating_sum(values: impl Iterator<Item = u64>) -> i64 {
+    values
+        .map(|v| i64::try_from(v).unwrap_or(i64::MAX))
+        .fold(0, i64::saturating_add)
+}
+
+impl<const D: usize> Default for PointN<D> {
+    fn default() -> Self {
+        PointN([0; D])
+    }
+}
+
+impl<const D: usize> Add for PointN<D> {
+    type Output = PointN<D>;
+
+    fn add(mut self, other: PointN<D>) -> PointN<D> {
+        for (a, b) in self.0.iter_mut().zip(other.0.iter()) {
+            *a += b;
+        }
+        self
+    }
+}
+
+impl<const D: usize> Index<usize> for PointN<D> {
+    type Output = i64;
+
+    fn index(&self, i: usize) -> &i64 {
+        &self.0[i]
+    }
+}
+
+impl<const D: usize> IndexMut<usize> for PointN<D> {
+    fn index_mut(&mut self, i: usize) -> &mut i64 {
+        &mut self.0[i]
+    }
+}
+
+impl From<Point> for PointN<2> {
+    fn from(p: Point) -> Self {
+        PointN([p.x, p.y])
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct Line {
+    pub p1: Point,
+    pub p2: Point,
+}
+
+impl Line {
+    pub fn new(p1: Point, p2: Point) -> Line {
+        Line { p1, p2 }
+    }
+
+    // Horizontal or vertical, like every wire segment of day 3.
+    pub fn is_axis_aligned(&self) -> bool {
+        self.p1.x == self.p2.x || self.p1.y == self.p2.y
+    }
+
+    // Checks whether the line segment contains
+    // the point.
+    pub fn contains(&self, p: &Point) -> bool {
+        let x1 = self.p1.x.min(self.p2.x);
+        let x2 = self.p1.x.max(self.p2.x);
+        let y1 = self.p1.y.min(self.p2.y);
+        let y2 = self.p1.y.max(self.p2.y);
+
+        // For horizontal or vertical lines the bounding box
+        // is the segment itself.
+        if p.x < x1 || p.x > x2 || p.y < y1 || p.y > y2 {
+            return false;
+        }
+        if self.is_axis_aligned() {
+            return true;
+        }
+
+        // Diagonals also need the point to be on the line.
+        let cross = (self.p2.x - self.p1.x) * (p.y - self.p1.y)
+            - (self.p2.y - self.p1.y) * (p.x - self.p1.x);
+        cross == 0
+    }
+
+    pub fn length(&self) -> i64 {
+        self.p1.distance(&self.p2)
+    }
+
+    pub fn intersects(&self, other: &Line) -> Option<Point> {
+        let a1 = self.p2.y - self.p1.y;
+        let b1 = self.p1.x - self.p2.x;
+        let c1 = a1 * self.p1.x + b1 * self.p1.y;
+
+        let a2 = other.p2.y - other.p1.y;
+        let b2 = other.p1.x - other.p2.x;
+        let c2 = a2 * other.p1.x + b2 * other.p1.y;
+
+        let determinant = a1 * b2 - a2 * b1;
+
+        if determinant == 0 {
+            self.overlap(other)
+        } else {
+            let x_num = b2 * c1 - b1 * c2;
+            let y_num = a1 * c2 - a2 * c1;
+
+            // Diagonals can cross between grid points, which a Point
+            // can't represent. See crossing for those.
+            if x_num % determinant != 0 || y_num % determinant != 0 {
+                return None;
+            }
+            let x = x_num / determinant;
+            let y = y_num / determinant;
+
+            // In this problem, origin doesn't count as
+            // a valid intersection point.
+            if x == 0 && y == 0 {
+                return None;
+            }
+
+            // We need to make sure that the point actually
+            // lies on both of the segments.
+            let p = Point::new(x, y);
+            if self.contains(&p) && other.contains(&p) {
+                Some(p)
+            } else {
+                None
+            }
+        }
+    }
+
+    // For segments on the same line, the shared point closest to the origin.
+    // The origin itself doesn't count, same as in intersects.
+    fn overlap(&self, other: &Line) -> Option<Point> {
+        let origin = Point::new(0, 0);
+        let (dx, dy) = (self.p2.x - self.p1.x, self.p2.y - self.p1.y);
+        let g = gcd(dx, dy);
+        if g == 0 {
+            // self is a single point.
+            return Some(self.p1).filter(|p| *p != origin && other.contains(p));
+        }
+
+        // Both ends of other have to be on the line through self.
+        let on_line = |p: &Point| dx * (p.y - self.p1.y) - dy * (p.x - self.p1.x) == 0;
+        if !on_line(&other.p1) || !on_line(&other.p2) {
+            return None;
+        }
+
+        // Points of the line are p1 + k * step for integer k, self covers
+        // k in 0..=g.
+        let (sx, sy) = (dx / g, dy / g);
+        let k_of = |p: &Point| {
+            if sx != 0 {
+                (p.x - self.p1.x) / sx
+            } else {
+                (p.y - self.p1.y) / sy
+            }
+        };
+        let (ka, kb) = (k_of(&other.p1), k_of(&other.p2));
+        let lo = ka.min(kb).max(0);
+        let hi = ka.max(kb).min(g);
+        if lo > hi {
+            return None;
+        }
+
+        // The distance to the origin is convex in k, so the closest point is
+        // an end of the overlap or next to where x or y crosses 0.
+        let mut candidates = vec![lo, hi];
+        for &(start, step) in [(self.p1.x, sx), (self.p1.y, sy)].iter() {
+            if step != 0 {
+                let k = (-start).div_euclid(step);
+                candidates.extend(k - 1..=k + 2);
+            }
+        }
+
+        candidates
+            .into_iter()
+            .filter(|k| (lo..=hi).contains(k))
+            .map(|k| Point::new(self.p1.x + k * sx, self.p1.y + k * sy))
+            .filter(|p| *p != origin)
+            .min_by_key(|p| p.distance(&origin))
+    }
+
+    // Where the two segments cross, including points between grid points.
+    // Parallel segments never cross, even when they overlap.
+    pub fn crossing(&self, other: &Line) -> Option<(f64, f64)> {
+        let (x1, y1) = (self.p1.x as f64, self.p1.y as f64);
+        let (dx1, dy1) = (
+            (self.p2.x - self.p1.x) as f64,
+            (self.p2.y - self.p1.y) as f64,
+        );
+        let (x2, y2) = (other.p1.x as f64, other.p1.y as f64);
+        let (dx2, dy2) = (
+            (other.p2.x - other.p1.x) as f64,
+            (other.p2.y - other.p1.y) as f64,
+        );
+
+        let denominator = dx1 * dy2 - dy1 * dx2;
+        if denominator == 0.0 {
+            return None;
+        }
+
+        // Positions along each segment, 0 at p1 and 1 at p2.
+        let t = ((x2 - x1) * dy2 - (y2 - y1) * dx2) / denominator;
+        let u = ((x2 - x1) * dy1 - (y2 - y1) * dx1) / denominator;
+        if (0.0..=1.0).contains(&t) && (0.0..=1.0).contains(&u) {
+            Some((x1 + t * dx1, y1 + t * dy1))
+        } else {
+            None
+        }
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Panel {
+    pub lines: Vec<Line>,
+    cursor: Point,
+}
+
+impl Panel {
+    pub fn new() -> Panel {
+        Panel {
+            lines: Vec::new(),
+            cursor: Point::new(0, 0),
+        }
+    }
+
+    // Builds a panel from a whole comma separated path like "R8,U5,L5".
+    pub fn from_path(path: &str) -> Result<Panel, PathError> {
+        let mut panel = Panel::new();
+        for (direction, distance) in parse_moves(path)? {
+            panel.push_move(direction, distance);
+        }
+
+        Ok(panel)
+    }
+
+    pub fn get_next_line(&self, path: &str) -> Line {
+        let direction = path.chars().next().unwrap();
+        let distance = path[1..].parse::<i64>().unwrap();
+        self.line_for(direction, distance)
+    }
+
+    fn line_for(&self, direction: char, distance: i64) -> Line {
+        let mut to = self.cursor;
+        match direction {
+            'R' => to.x += distance,
+            'L' => to.x -= distance,
+            'U' => to.y += distance,
+            'D' => to.y -= distance,
+            x => panic!("Unexpected direction: {}", x),
+        }
+
+        Line::new(self.cursor, to)
+    }
+
+    pub fn insert(&mut self, path: &str) {
+        let line = self.get_next_line(path);
+        self.push_line(line);
+    }
+
+    fn push_move(&mut self, direction: char, distance: i64) {
+        let line = self.line_for(direction, distance);
+        self.push_line(line);
+    }
+
+    fn push_line(&mut self, line: Line) {
+        self.lines.push(line);
+
+        // Update cursor location.
+        self.cursor = Point::new(line.p2.x, line.p2.y);
+    }
+
+    // Checks whether any segment of the wire covers the point.
+    pub fn passes_through(&self, p: &Point) -> bool {
+        self.lines.iter().any(|l| l.contains(p))
+    }
+
+    // Every point where input crosses one of the panel's lines, in the
+    // order the lines were inserted.
+    pub fn all_intersections(&self, input: &Line) -> Vec<Point> {
+        self.lines
+            .iter()
+            .filter_map(|l| l.intersects(input))
+            .collect()
+    }
+
+    // Distance from the origin to the closest crossing with input.
+    pub fn find_intersection(&self, input: &Line) -> Option<i64> {
+        let origin = Point::new(0, 0);
+        self.all_intersections(input)
+            .iter()
+            .map(|p| p.distance(&origin))
+            .min()
+    }
+
+    // Returns the cost of intersection in terms of panel's wiring.
+    pub fn find_intersection_cost(&self, input: &Line) -> Option<Vec<(Point, i64)>> {
+        let mut result = vec![];
+        let mut distance = 0;
+        for l in self.lines.iter() {
+            match l.intersects(input) {
+                Some(p) => {
+                    distance += l.p1.distance(&p);
+                    result.push((p, distance));
+                }
+                None => {
+                    distance += l.length();
+                }
+            }
+        }
+
+        if result.is_empty() {
+            return None;
+        }
+
+        Some(result)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum PathError {
+    // The token at index (0 based) isn't a direction followed by a distance.
+    BadToken { index: usize, token: String },
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PathError::BadToken { index, token } => {
+                write!(f, "bad token {:?} at index {}", token, index)
+            }
+        }
+    }
+}
+
+impl Error for PathError {}
+
+fn is_valid_move(token: &str) -> bool {
+    let mut chars = token.chars();
+    match chars.next() {
+        Some('R') | Some('L') | Some('U') | Some('D') => (),
+        _ => return false,
+    }
+
+    let distance = chars.as_str();
+    !distance.is_empty() && distance.chars().all(|c| c.is_ascii_digit())
+}
+
+// Decodes a comma separated wire path like "R8,U5,L5" into
+// (direction, distance) pairs.
+pub fn parse_moves(path: &str) -> Result<Vec<(char, i64)>, PathError> {
+    let mut moves = vec![];
+    for (index, token) in path.trim().split(',').enumerate() {
+        // The distance can still overflow i64 after passing the digit check.
+        let distance = if is_valid_move(token) {
+            token[1..].parse::<i64>().ok()
+        } else {
+            None
+        };
+
+        match distance {
+            Some(d) => moves.push((token.chars().next().unwrap(), d)),
+            None => {
+                return Err(PathError::BadToken {
+                    index,
+                    token: token.to_owned(),
+                })
+            }
+        }
+    }
+
+    Ok(moves)
+}
+
+// Checks a comma separated wire path like "R8,U5,L5" without building a Panel.
+pub fn validate_path(path: &str) -> Result<(), PathError> {
+    parse_moves(path).map(|_| ())
+}
+
+// Shifts the points so that the smallest x and y land on 0. Returns the
+// shifted points along with the offset that was added to each of them.
+pub fn normalize_points(points: &[Point]) -> (Vec<Point>, Point) {
+    let min_x = points.iter().map(|p| p.x).min().unwrap_or(0);
+    let min_y = points.iter().map(|p| p.y).min().unwrap_or(0);
+    let offset = Point::new(-min_x, -min_y);
+
+    let shifted = points
+        .iter()
+        .map(|p| Point::new(p.x + offset.x, p.y + offset.y))
+        .collect();
+
+    (shifted, offset)
+}
+
+#[derive(Debug, PartialEq)]
+pub struct IntersectionStats {
+    pub count: usize,
+    pub nearest_manhattan: i64,
+    pub farthest_manhattan: i64,
+    pub cheapest_steps: i64,
+}
+
+// Collects all the day 3 numbers in a single pass over the crossings of the
+// two wires.
+pub fn intersection_stats(wire1: Vec<&str>, wire2: Vec<&str>) -> Option<IntersectionStats> {
+    let mut panel = Panel::new();
+    for x in wire1.iter() {
+        panel.insert(x);
+    }
+
+    let origin = Point::new(0, 0);
+    let mut cost = 0;
+    let mut other = Panel::new();
+    let mut stats: Option<IntersectionStats> = None;
+    for w in wire2.iter() {
+        let l = other.get_next_line(w);
+        other.insert(w);
+        if let Some(v) = panel.find_intersection_cost(&l) {
+            for (p, wire1_cost) in v.iter() {
+                let steps = cost + wire1_cost + l.p1.distance(p);
+                let distance = p.distance(&origin);
+                let s = stats.get_or_insert(IntersectionStats {
+                    count: 0,
+                    nearest_manhattan: distance,
+                    farthest_manhattan: distance,
+                    cheapest_steps: steps,
+                });
+                s.count += 1;
+                s.nearest_manhattan = s.nearest_manhattan.min(distance);
+                s.farthest_manhattan = s.farthest_manhattan.max(distance);
+                s.cheapest_steps = s.cheapest_steps.min(steps);
+            }
+        }
+        cost += l.length();
+    }
+
+    stats
+}
+
+// Every cell the wire passes through along with the number of steps it took
+// to get there the first time. The origin isn't included, even if the wire
+// comes back to it.
+pub fn walk_wire(path: &str) -> Result<HashMap<Point, i64>, PathError> {
+    let origin = Point::new(0, 0);
+    let mut visited = HashMap::new();
+    let mut cursor = origin;
+    let mut steps = 0;
+    for (direction, distance) in parse_moves(path)? {
+        // parse_moves only lets R, L, U and D through.
+        let (dx, dy) = match direction {
+            'R' => (1, 0),
+            'L' => (-1, 0),
+            'U' => (0, 1),
+            _ => (0, -1),
+        };
+        for _ in 0..distance {
+            cursor = Point::new(cursor.x + dx, cursor.y + dy);
+            steps += 1;
+            if cursor != origin {
+                visited.entry(cursor).or_insert(steps);
+            }
+        }
+    }
+
+    Ok(visited)
+}
+
+// Distance from the origin to the closest crossing of the two wires, found by
+// walking both wires cell by cell. The cost depends on the wire lengths
+// rather than the number of segments.
+pub fn find_closest_intersection_grid(
+    wire1: Vec<&str>,
+    wire2: Vec<&str>,
+) -> Result<Option<i64>, PathError> {
+    let cells1 = walk_wire(&wire1.join(","))?;
+    let cells2 = walk_wire(&wire2.join(","))?;
+    let origin = Point::new(0, 0);
+
+    Ok(cells1
+        .keys()
+        .filter(|p| cells2.contains_key(p))
+        .map(|p| p.distance(&origin))
+        .min())
+}
+
+// Fewest combined steps the two wires take to reach a crossing, using the
+// cell by cell walk.
+pub fn find_cheapest_intersection_grid(
+    wire1: Vec<&str>,
+    wire2: Vec<&str>,
+) -> Result<Option<i64>, PathError> {
+    let cells1 = walk_wire(&wire1.join(","))?;
+    let cells2 = walk_wire(&wire2.join(","))?;
+
+    Ok(cells1
+        .iter()
+        .filter_map(|(p, steps1)| cells2.get(p).map(|steps2| steps1 + steps2))
+        .min())
+}
+
+// Builds a panel for each wire and returns every crossing between each pair
+// of distinct wires along with the indices of the two wires involved.
+pub fn intersections_among(wires: &[Vec<&str>]) -> Vec<(usize, usize, Point)> {
+    let panels: Vec<Panel> = wires
+        .iter()
+        .map(|w| {
+            let mut panel = Panel::new();
+            for x in w.iter() {
+                panel.insert(x);
+            }
+            panel
+        })
+        .collect();
+
+    let panels: Vec<&Panel> = panels.iter().collect();
+    panel_intersections(&panels)
+}
+
+// Same as intersections_among for panels that are already built.
+fn panel_intersections(panels: &[&Panel]) -> Vec<(usize, usize, Point)> {
+    let mut result = vec![];
+    for (i, p1) in panels.iter().enumerate() {
+        for (j, p2) in panels.iter().enumerate().skip(i + 1) {
+            for l1 in p1.lines.iter() {
+                for l2 in p2.lines.iter() {
+                    if let Some(p) = l1.intersects(l2) {
+                        result.push((i, j, p));
+                    }
+                }
+            }
+        }
+    }
+
+    result
+}
+
+// Draws each panel as a polyline with a circle at every crossing between two
+// different panels. SVG's y axis points down so y is flipped to keep "U"
+// pointing up. The viewBox is the bounding box with a margin of 1 and the
+// circles grow with it so they stay visible at any scale.
+pub fn panels_to_svg(panels: &[&Panel]) -> String {
+    let paths: Vec<Vec<Point>> = panels
+        .iter()
+        .map(|panel| {
+            let mut points: Vec<Point> = panel.lines.iter().take(1).map(|l| l.p1).collect();
+            points.extend(panel.lines.iter().map(|l| l.p2));
+            points
+        })
+        .collect();
+
+    let crossings = panel_intersections(panels);
+
+    let all = paths.iter().flatten();
+    let min_x = all.clone().map(|p| p.x).min().unwrap_or(0);
+    let max_x = all.clone().map(|p| p.x).max().unwrap_or(0);
+    let min_y = all.clone().map(|p| -p.y).min().unwrap_or(0);
+    let max_y = all.map(|p| -p.y).max().unwrap_or(0);
+
+    let (width, height) = (max_x - min_x + 2, max_y - min_y + 2);
+    let radius = (width.max(height) as f64 / 100.0).max(0.5);
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+        min_x - 1,
+        min_y - 1,
+        width,
+        height
+    );
+
+    for path in paths.iter() {
+        let points: Vec<String> = path.iter().map(|p| format!("{},{}", p.x, -p.y)).collect();
+        svg.push_str(&format!(
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"black\" vector-effect=\"non-scaling-stroke\"/>\n",
+            points.join(" ")
+        ));
+    }
+
+    for (_, _, p) in crossings.iter() {
+        svg.push_str(&format!(
+            "<circle cx=\"{}\" cy=\"{}\" r=\"{}\" fill=\"red\"/>\n",
+            p.x, -p.y, radius
+        ));
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_intersection() {
+        let l1 = Line::new(Point::new(6, 3), Point::new(6, 7));
+        let l2 = Line::new(Point::new(3, 5), Point::new(8, 5));
+        assert_eq!(l1.intersects(&l2), Some(Point::new(6, 5)));
+
+        let l1 = Line::new(Point::new(0, 0), Point::new(8, 0));
+        let l2 = Line::new(Point::new(0, 1), Point::new(0, 7));
+        assert_eq!(l1.intersects(&l2), None);
+
+        let l1 = Line::new(Point::new(66, 62), Point::new(66, 117));
+        let l2 = Line::new(Point::new(0, 0), Point::new(75, 0));
+        assert_eq!(l1.intersects(&l2), None);
+        assert_eq!(l2.intersects(&l1), None);
+    }
+
+    #[test]
+    fn test_collinear_overlap() {
+        // Both on y = 2 and sharing 3..=6.
+        let l1 = Line::new(Point::new(-1, 2), Point::new(6, 2));
+        let l2 = Line::new(Point::new(10, 2), Point::new(3, 2));
+        assert_eq!(l1.intersects(&l2), Some(Point::new(3, 2)));
+        assert_eq!(l2.intersects(&l1), Some(Point::new(3, 2)));
+
+        // The overlap contains the point straight above the origin.
+        let l3 = Line::new(Point::new(-4, 2), Point::new(1, 2));
+        assert_eq!(l1.intersects(&l3), Some(Point::new(0, 2)));
+
+        // Parallel but on different rows, or on the same row without overlap.
+        let l4 = Line::new(Point::new(0, 3), Point::new(6, 3));
+        assert_eq!(l1.intersects(&l4), None);
+        let l5 = Line::new(Point::new(7, 2), Point::new(9, 2));
+        assert_eq!(l1.intersects(&l5), None);
+
+        // The origin doesn't count, the next closest shared point does.
+        let l6 = Line::new(Point::new(-3, 0), Point::new(5, 0));
+        let l7 = Line::new(Point::new(0, 0), Point::new(2, 0));
+        assert_eq!(l6.intersects(&l7), Some(Point::new(1, 0)));
+
+        // Overlapping diagonals.
+        let l8 = Line::new(Point::new(0, 0), Point::new(6, 6));
+        let l9 = Line::new(Point::new(8, 8), Point::new(2, 2));
+        assert_eq!(l8.intersects(&l9), Some(Point::new(2, 2)));
+    }
+
+    #[test]
+    fn test_diagonal_intersection() {
+        let l1 = Line::new(Point::new(0, 4), Point::new(4, 0));
+        let l2 = Line::new(Point::new(1, 1), Point::new(5, 5));
+        assert_eq!(l1.intersects(&l2), Some(Point::new(2, 2)));
+        assert_eq!(l1.crossing(&l2), Some((2.0, 2.0)));
+
+        // Diagonal against vertical.
+        let l3 = Line::new(Point::new(3, -2), Point::new(3, 8));
+        assert_eq!(l2.intersects(&l3), Some(Point::new(3, 3)));
+
+        // Crosses at (1.5, 1.5) which isn't a grid point.
+        let l1 = Line::new(Point::new(0, 3), Point::new(3, 0));
+        assert_eq!(l1.intersects(&l2), None);
+        assert_eq!(l1.crossing(&l2), Some((1.5, 1.5)));
+
+        // The lines cross at (6, 6), past the end of both segments.
+        let l1 = Line::new(Point::new(0, 12), Point::new(4, 8));
+        assert_eq!(l1.intersects(&l2), None);
+        assert_eq!(l1.crossing(&l2), None);
+
+        assert!(l2.contains(&Point::new(4, 4)));
+        assert!(!l2.contains(&Point::new(4, 3)));
+    }
+
+    #[test]
+    fn test_distance_large_coordinates() {
+        let p1 = Point::new(i64::MAX, i64::MAX);
+        let p2 = Point::new(i64::MIN, i64::MIN);
+        assert_eq!(p1.distance(&p2), i64::MAX);
+
+        let p1 = Point::new(i64::MAX, 0);
+        let p2 = Point::new(i64::MAX - 10, 5);
+        assert_eq!(p1.distance(&p2), 15);
+    }
+
+    #[test]
+    fn test_point_n() {
+        let a = PointN::new([1, -2, 3]);
+        let b = PointN::new([-4, 5, 0]);
+        assert_eq!(a + b, PointN::new([-3, 3, 3]));
+        assert_eq!(a.manhattan(), 6);
+        assert_eq!(a.distance(&b), 15);
+        assert_eq!(PointN::<3>::default().distance(&a), a.manhattan());
+
+        let mut c = a;
+        c[1] += 2;
+        assert_eq!(c[1], 0);
+        assert_eq!(c, PointN::new([1, 0, 3]));
+
+        let p = PointN::from(Point::new(3, -4));
+        assert_eq!(p.distance(&PointN::default()), 7);
+    }
+
+    #[test]
+    fn test_point_n_large_coordinates() {
+        let p1 = PointN::new([i64::MAX, i64::MAX, i64::MAX]);
+        let p2 = PointN::new([i64::MIN, i64::MIN, i64::MIN]);
+        assert_eq!(p1.distance(&p2), i64::MAX);
+        assert_eq!(p2.manhattan(), i64::MAX);
+
+        let p1 = PointN::new([i64::MAX, 0, i64::MIN]);
+        let p2 = PointN::new([i64::MAX - 10, 5, i64::MIN + 1]);
+        assert_eq!(p1.distance(&p2), 16);
+    }
+
+    #[test]
+    fn test_passes_through() {
+        let mut panel = Panel::new();
+        panel.insert("R8");
+        panel.insert("U5");
+        assert!(panel.passes_through(&Point::new(8, 3)));
+        assert!(panel.passes_through(&Point::new(4, 0)));
+        assert!(!panel.passes_through(&Point::new(1, 1)));
+    }
+
+    #[test]
+    fn test_all_intersections() {
+        let mut panel = Panel::new();
+        for m in ["R8", "U5", "L5", "D3"].iter() {
+            panel.insert(m);
+        }
+
+        let line = Line::new(Point::new(0, 3), Point::new(10, 3));
+        assert_eq!(
+            panel.all_intersections(&line),
+            vec![Point::new(8, 3), Point::new(3, 3)]
+        );
+        assert_eq!(panel.find_intersection(&line), Some(6));
+
+        let line = Line::new(Point::new(0, 7), Point::new(10, 7));
+        assert!(panel.all_intersections(&line).is_empty());
+        assert_eq!(panel.find_intersection(&line), None);
+    }
+
+    #[test]
+    fn test_panels_to_svg() {
+        let mut p1 = Panel::new();
+        for m in ["R8", "U5", "L5", "D3"].iter() {
+            p1.insert(m);
+        }
+        let mut p2 = Panel::new();
+        for m in ["U7", "R6", "D4", "L4"].iter() {
+            p2.insert(m);
+        }
+
+        let svg = panels_to_svg(&[&p1, &p2]);
+        assert!(svg.contains("viewBox=\"-1 -8 10 9\""));
+        assert_eq!(svg.matches("<polyline").count(), 2);
+        assert!(svg.contains("points=\"0,0 8,0 8,-5 3,-5 3,-2\""));
+        assert!(svg.contains("<circle cx=\"6\" cy=\"-5\""));
+        assert!(svg.contains("<circle cx=\"3\" cy=\"-3\" r=\"0.5\""));
+
+        // At day 3 scale the circles grow with the viewBox.
+        let p1 = Panel::from_path("R10000").unwrap();
+        let p2 = Panel::from_path("U5000,R5000,D10000").unwrap();
+        let svg = panels_to_svg(&[&p1, &p2]);
+        assert!(svg.contains("viewBox=\"-1 -5001 10002 10002\""));
+        assert!(svg.contains("<circle cx=\"5000\" cy=\"0\" r=\"100.02\""));
+    }
+
+    #[test]
+    fn test_intersection_stats() {
+        let wire1 = vec!["R8", "U5", "L5", "D3"];
+        let wire2 = vec!["U7", "R6", "D4", "L4"];
+        assert_eq!(
+            intersection_stats(wire1, wire2),
+            Some(IntersectionStats {
+                count: 2,
+                nearest_manhattan: 6,
+                farthest_manhattan: 11,
+                cheapest_steps: 30,
+            })
+        );
+
+        assert_eq!(intersection_stats(vec!["R8"], vec!["U7"]), None);
+    }
+
+    #[test]
+    fn test_walk_wire() {
+        let cells = walk_wire("R2,U1,L2,D1").unwrap();
+        assert_eq!(cells.len(), 5);
+        assert_eq!(cells[&Point::new(2, 1)], 3);
+        // The wire comes back to the origin, which still isn't included.
+        assert!(!cells.contains_key(&Point::new(0, 0)));
+        assert!(walk_wire("R2,X1").is_err());
+    }
+
+    #[test]
+    fn test_grid_intersections() {
+        let wire1 = vec!["R8", "U5", "L5", "D3"];
+        let wire2 = vec!["U7", "R6", "D4", "L4"];
+        assert_eq!(
+            find_closest_intersection_grid(wire1.clone(), wire2.clone()),
+            Ok(Some(6))
+        );
+        assert_eq!(find_cheapest_intersection_grid(wire1, wire2), Ok(Some(30)));
+        assert_eq!(
+            find_cheapest_intersection_grid(vec!["R8"], vec!["U7"]),
+            Ok(None)
+        );
+        assert_eq!(
+            find_closest_intersection_grid(vec!["R8"], vec!["U7", "7R"]),
+            Err(PathError::BadToken {
+                index: 1,
+                token: "7R".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_intersections_among() {
+        let wires = vec![
+            vec!["R8", "U5", "L5", "D3"],
+            vec!["U7", "R6", "D4", "L4"],
+            vec!["U2", "R10"],
+        ];
+
+        let crossings = intersections_among(&wires);
+        let expected = [
+            (0, 1, Point::new(6, 5)),
+            (0, 1, Point::new(3, 3)),
+            (0, 2, Point::new(8, 2)),
+            (0, 2, Point::new(3, 2)),
+            (1, 2, Point::new(0, 2)),
+            // U7 and U2 overlap, (0, 1) is their shared point closest to
+            // the origin.
+            (1, 2, Point::new(0, 1)),
+        ];
+        assert_eq!(crossings.len(), expected.len());
+        for e in expected.iter() {
+            assert!(crossings.contains(e), "missing {:?}", e);
+        }
+    }
+
+    #[test]
+    fn test_validate_path() {
+        assert_eq!(validate_path("R8,U5,L5,D3"), Ok(()));
+        assert_eq!(
+            validate_path("R8,U5,X5,D3"),
+            Err(PathError::BadToken {
+                index: 2,
+                token: "X5".to_owned()
+            })
+        );
+        assert!(validate_path("R8,U").is_err());
+    }
+
+    #[test]
+    fn test_parse_moves() {
+        assert_eq!(parse_moves("R8,U5"), Ok(vec![('R', 8), ('U', 5)]));
+        assert_eq!(
+            parse_moves("R8,5U"),
+            Err(PathError::BadToken {
+                index: 1,
+                token: "5U".to_owned()
+            })
+        );
+    }
+
+    #[test]
+    fn test_panel_from_path() {
+        let panel = Panel::from_path("R8,U5,L5,D3").unwrap();
+        assert_eq!(panel.lines.len(), 4);
+        assert_eq!(panel.lines[3].p2, Point::new(3, 2));
+        assert!(Panel::from_path("R8,Q1").is_err());
+    }
+
+    #[test]
+    fn test_normalize_points() {
+        let points = vec![Point::new(-3, 2), Point::new(4, -5), Point::new(0, 0)];
+        let (shifted, offset) = normalize_points(&points);
+
+        assert_eq!(offset, Point::new(3, 5));
+        assert_eq!(
+            shifted,
+            vec![Point::new(0, 7), Point::new(7, 0), Point::new(3, 5)]
+        );
+    }
+}
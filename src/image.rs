@@ -0,0 +1,238 @@
+// Space Image Format helpers used by day 8.
+
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, PartialEq)]
+pub enum ImageError {
+    // Width or height is 0.
+    EmptyLayer,
+    // The digits don't split into whole layers.
+    RaggedLayers { digits: usize, layer_size: usize },
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImageError::EmptyLayer => write!(f, "layers must have a non-zero size"),
+            ImageError::RaggedLayers { digits, layer_size } => write!(
+                f,
+                "{} digits can't be split into layers of {}",
+                digits, layer_size
+            ),
+        }
+    }
+}
+
+impl Error for ImageError {}
+
+// Number of layers in an image of digits_len digits.
+pub fn layer_count(digits_len: usize, width: usize, height: usize) -> Result<usize, ImageError> {
+    let layer_size = width * height;
+    if layer_size == 0 {
+        return Err(ImageError::EmptyLayer);
+    }
+    if digits_len % layer_size != 0 {
+        return Err(ImageError::RaggedLayers {
+            digits: digits_len,
+            layer_size,
+        });
+    }
+
+    Ok(digits_len / layer_size)
+}
+
+// Splits the digits into width x height layers, failing if they don't divide
+// evenly.
+pub fn split_layers(
+    digits: &[u32],
+    width: usize,
+    height: usize,
+) -> Result<Vec<&[u32]>, ImageError> {
+    layer_count(digits.len(), width, height)?;
+    Ok(digits.chunks(width * height).collect())
+}
+
+// Returns (number of zeros, number of ones * number of twos) for a layer.
+pub fn calculate_layer(layer: &[u32]) -> (u64, u64) {
+    let mut zero_count = 0;
+    let mut one_count = 0;
+    let mut two_count = 0;
+
+    for &i in layer {
+        match i {
+            0 => zero_count += 1,
+            1 => one_count += 1,
+            2 => two_count += 1,
+            _ => (),
+        }
+    }
+
+    (zero_count, one_count * two_count)
+}
+
+// Part 1: ones * twos of the layer with the fewest zeros.
+pub fn checksum(layers: &[&[u32]]) -> u64 {
+    let mut min = u64::MAX;
+    let mut result = 0;
+    for c in layers {
+        let counts = calculate_layer(c);
+        if counts.0 < min {
+            result = counts.1;
+            min = counts.0;
+        }
+    }
+
+    result
+}
+
+// Pixel values of an image. The default is the AoC encoding.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Palette {
+    pub lit: u32,
+    pub dark: u32,
+    pub transparent: u32,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette {
+            lit: 1,
+            dark: 0,
+            transparent: 2,
+        }
+    }
+}
+
+// Part 2: stacks the layers so that the first non-transparent pixel wins.
+pub fn composite(layers: &[&[u32]], size: usize) -> Vec<u32> {
+    composite_with(layers, size, &Palette::default())
+}
+
+pub fn composite_with(layers: &[&[u32]], size: usize, palette: &Palette) -> Vec<u32> {
+    // initially the image is transparent.
+    let mut image: Vec<u32> = vec![palette.transparent; size];
+
+    for (i, pixel) in image.iter_mut().enumerate() {
+        // transparent is a good default.
+        let mut color = palette.transparent;
+        for l in layers.iter() {
+            if l[i] != palette.transparent {
+                color = l[i];
+                break;
+            }
+        }
+
+        *pixel = color;
+    }
+
+    image
+}
+
+// Number of pixels that are lit (1) in a composited image.
+pub fn count_lit_pixels(image: &[u32]) -> usize {
+    count_lit_pixels_with(image, &Palette::default())
+}
+
+pub fn count_lit_pixels_with(image: &[u32], palette: &Palette) -> usize {
+    image.iter().filter(|&&p| p == palette.lit).count()
+}
+
+pub fn render(image: &[u32], width: usize, height: usize) -> String {
+    render_with(image, width, height, &Palette::default())
+}
+
+// Dark and transparent pixels are left blank.
+pub fn render_with(image: &[u32], width: usize, height: usize, palette: &Palette) -> String {
+    let mut result = String::new();
+    for i in 0..height {
+        for j in 0..width {
+            let c = image[i * width + j];
+            if c == palette.transparent || c == palette.dark {
+                result.push_str("  ");
+            } else {
+                result.push_str(&format!("{} ", c));
+            }
+        }
+        result.push('\n');
+    }
+
+    result
+}
+
+// Solves both parts of day 8 from a single parse of the digits.
+pub fn day8(digits: &[u32], width: usize, height: usize) -> Result<(u64, String), ImageError> {
+    let layers = split_layers(digits, width, height)?;
+    let image = composite(&layers, width * height);
+    Ok((checksum(&layers), render(&image, width, height)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_day8() {
+        let digits: Vec<u32> = "0222112222120000"
+            .chars()
+            .map(|c| c.to_digit(10).unwrap())
+            .collect();
+
+        let (checksum, image) = day8(&digits, 2, 2).unwrap();
+        assert_eq!(checksum, 4);
+        assert_eq!(image, "  1 \n1   \n");
+
+        assert_eq!(
+            day8(&digits[..15], 2, 2),
+            Err(ImageError::RaggedLayers {
+                digits: 15,
+                layer_size: 4
+            })
+        );
+        assert_eq!(day8(&digits, 0, 2), Err(ImageError::EmptyLayer));
+    }
+
+    #[test]
+    fn test_layer_count() {
+        assert_eq!(layer_count(16, 2, 2), Ok(4));
+        assert_eq!(layer_count(15000, 25, 6), Ok(100));
+        assert_eq!(
+            layer_count(17, 2, 2),
+            Err(ImageError::RaggedLayers {
+                digits: 17,
+                layer_size: 4
+            })
+        );
+        assert_eq!(layer_count(4, 0, 2), Err(ImageError::EmptyLayer));
+    }
+
+    #[test]
+    fn test_split_layers() {
+        let digits = [1, 2, 3, 4, 5, 6];
+        let layers = split_layers(&digits, 3, 1).unwrap();
+        assert_eq!(layers, vec![&[1, 2, 3][..], &[4, 5, 6][..]]);
+        assert!(split_layers(&digits, 2, 2).is_err());
+    }
+
+    #[test]
+    fn test_count_lit_pixels() {
+        let layers: [&[u32]; 4] = [&[0, 2, 2, 2], &[1, 1, 2, 2], &[2, 2, 1, 2], &[0, 0, 0, 0]];
+        let image = composite(&layers, 4);
+        assert_eq!(image, vec![0, 1, 1, 0]);
+        assert_eq!(count_lit_pixels(&image), 2);
+    }
+
+    #[test]
+    fn test_custom_palette() {
+        let palette = Palette {
+            lit: 5,
+            dark: 3,
+            transparent: 0,
+        };
+        let layers: [&[u32]; 3] = [&[3, 0, 0, 0], &[5, 5, 0, 0], &[0, 0, 5, 3]];
+        let image = composite_with(&layers, 4, &palette);
+        assert_eq!(image, vec![3, 5, 5, 3]);
+        assert_eq!(count_lit_pixels_with(&image, &palette), 2);
+        assert_eq!(render_with(&image, 2, 2, &palette), "  5 \n5   \n");
+    }
+}
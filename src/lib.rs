@@ -1,18 +1,168 @@
+use std::error::Error;
+use std::fmt;
 use std::fs;
+use std::io::{self, Read};
+
+pub mod error;
+pub mod fuel;
+pub mod geometry;
+pub mod image;
+pub mod orbit;
 pub mod vm;
 
-pub fn read_input(path: &str) -> Vec<i64> {
-    let contents = fs::read_to_string(path).unwrap();
-    contents
-        .lines()
-        .map(|l| l.trim().parse::<i64>().unwrap())
-        .collect()
+#[derive(Debug)]
+pub enum InputError {
+    Io(io::Error),
+    // 1 based line number and the text that failed to parse.
+    Parse { line: usize, text: String },
+    // Byte offset of a comma separated token that isn't an integer.
+    BadToken { offset: usize, token: String },
+}
+
+impl fmt::Display for InputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InputError::Io(e) => write!(f, "{}", e),
+            InputError::Parse { line, text } => {
+                write!(f, "line {}: can't parse {:?} as an integer", line, text)
+            }
+            InputError::BadToken { offset, token } => {
+                write!(f, "byte {}: can't parse {:?} as an integer", offset, token)
+            }
+        }
+    }
+}
+
+impl Error for InputError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            InputError::Io(e) => Some(e),
+            InputError::Parse { .. } | InputError::BadToken { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for InputError {
+    fn from(e: io::Error) -> Self {
+        InputError::Io(e)
+    }
+}
+
+// Reads one integer per line. Blank lines are skipped.
+pub fn read_input(path: &str) -> Result<Vec<i64>, InputError> {
+    let contents = fs::read_to_string(path)?;
+    let mut result = vec![];
+    for (i, l) in contents.lines().enumerate() {
+        let text = l.trim();
+        if text.is_empty() {
+            continue;
+        }
+
+        let v = text.parse::<i64>().map_err(|_| InputError::Parse {
+            line: i + 1,
+            text: text.to_owned(),
+        })?;
+        result.push(v);
+    }
+
+    Ok(result)
+}
+
+pub fn read_input_or_panic(path: &str) -> Vec<i64> {
+    match read_input(path) {
+        Ok(v) => v,
+        Err(e) => panic!("{}: {}", path, e),
+    }
 }
 
+// Tokens that aren't integers are skipped, see read_csv_ints_strict.
 pub fn read_csv_ints(path: &str) -> Vec<i64> {
-    let contents = fs::read_to_string(path).unwrap();
+    let contents = match fs::File::open(path)
+        .map_err(InputError::from)
+        .and_then(read_text)
+    {
+        Ok(c) => c,
+        Err(e) => panic!("{}: {}", path, e),
+    };
     contents
-        .split(",")
+        .split(',')
         .filter_map(|v| v.parse::<i64>().ok())
         .collect()
 }
+
+// Like read_csv_ints but every token has to be an integer. Only trailing
+// whitespace at the end of the file is ignored.
+pub fn read_csv_ints_strict(path: &str) -> Result<Vec<i64>, InputError> {
+    read_csv_ints_from(fs::File::open(path)?)
+}
+
+// Same as read_csv_ints_strict for any reader, e.g. stdin or a byte slice.
+pub fn read_csv_ints_from<R: Read>(reader: R) -> Result<Vec<i64>, InputError> {
+    let contents = read_text(reader)?;
+    let mut result = vec![];
+    let mut offset = 0;
+    for token in contents.trim_end().split(',') {
+        let v = token.parse::<i64>().map_err(|_| InputError::BadToken {
+            offset,
+            token: token.to_owned(),
+        })?;
+        result.push(v);
+        offset += token.len() + 1;
+    }
+
+    Ok(result)
+}
+
+fn read_text<R: Read>(mut reader: R) -> Result<String, InputError> {
+    let mut contents = String::new();
+    reader.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_input() {
+        let v = read_input("assets/day1_input").unwrap();
+        assert_eq!(v.len(), 100);
+
+        match read_input("assets/day2_input") {
+            Err(InputError::Parse { line: 1, .. }) => (),
+            r => panic!("unexpected result: {:?}", r),
+        }
+
+        assert!(matches!(
+            read_input("assets/no_such_file"),
+            Err(InputError::Io(_))
+        ));
+    }
+
+    #[test]
+    fn test_read_csv_ints_from() {
+        assert_eq!(
+            read_csv_ints_from(&b"3,0,4,0,99\n"[..]).unwrap(),
+            vec![3, 0, 4, 0, 99]
+        );
+
+        match read_csv_ints_from(&b"1, 2"[..]) {
+            Err(InputError::BadToken { offset: 2, token }) => assert_eq!(token, " 2"),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_read_csv_ints_strict() {
+        // The lenient reader drops the last value because of the newline.
+        let strict = read_csv_ints_strict("assets/day2_input").unwrap();
+        let lenient = read_csv_ints("assets/day2_input");
+        assert_eq!(strict.len(), lenient.len() + 1);
+        assert_eq!(&strict[..lenient.len()], &lenient[..]);
+
+        match read_csv_ints_strict("assets/day1_input") {
+            Err(InputError::BadToken { offset: 0, .. }) => (),
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,239 @@
+// Orbit maps for day 6.
+
+use std::collections::HashMap;
+
+use crate::error::OrbitError;
+
+#[derive(Debug)]
+pub struct Entry {
+    pub target: String,
+    pub sattelite: String,
+}
+
+// returns (source, sattelite)
+fn split(s: &str) -> Result<Entry, OrbitError> {
+    let mut vals = s.split(')');
+    match (vals.next(), vals.next(), vals.next()) {
+        (Some(target), Some(sattelite), None) if !target.is_empty() && !sattelite.is_empty() => {
+            Ok(Entry {
+                target: target.to_owned(),
+                sattelite: sattelite.to_owned(),
+            })
+        }
+        _ => Err(OrbitError::MalformedEntry(s.to_owned())),
+    }
+}
+
+// Parses one "A)B" entry per line, meaning B orbits A.
+pub fn parse_entries(contents: &str) -> Result<Vec<Entry>, OrbitError> {
+    contents.trim().lines().map(split).collect()
+}
+
+fn build_map(input: Vec<Entry>) -> HashMap<String, String> {
+    //  Key is the sattelite and value is the target.
+    let mut map = HashMap::new();
+    for v in input.iter() {
+        map.insert(v.sattelite.clone(), v.target.clone());
+    }
+
+    map
+}
+
+#[derive(Debug)]
+pub struct OrbitGraph {
+    // Key is the sattelite and value is the target.
+    map: HashMap<String, String>,
+    // Key is the target and value is everything orbiting it directly.
+    children: HashMap<String, Vec<String>>,
+}
+
+impl OrbitGraph {
+    pub fn new(input: Vec<Entry>) -> OrbitGraph {
+        let mut children: HashMap<String, Vec<String>> = HashMap::new();
+        for v in input.iter() {
+            children
+                .entry(v.target.clone())
+                .or_default()
+                .push(v.sattelite.clone());
+        }
+
+        OrbitGraph {
+            map: build_map(input),
+            children,
+        }
+    }
+
+    // Sum of the depths of all bodies, i.e. the number of direct and
+    // indirect orbits. Each body is visited once starting from the bodies
+    // that don't orbit anything.
+    pub fn total_orbits(&self) -> usize {
+        let mut total = 0;
+        let mut stack: Vec<(&str, usize)> = self
+            .children
+            .keys()
+            .filter(|k| !self.map.contains_key(*k))
+            .map(|k| (k.as_str(), 0))
+            .collect();
+
+        while let Some((body, depth)) = stack.pop() {
+            total += depth;
+            if let Some(children) = self.children.get(body) {
+                stack.extend(children.iter().map(|c| (c.as_str(), depth + 1)));
+            }
+        }
+
+        total
+    }
+
+    // Whether body orbits something or is orbited by something.
+    pub fn contains(&self, body: &str) -> bool {
+        self.map.contains_key(body) || self.children.contains_key(body)
+    }
+
+    // Number of orbital transfers to move from the body a orbits to the
+    // body b orbits.
+    pub fn transfers(&self, a: &str, b: &str) -> Result<usize, OrbitError> {
+        if let Some(unknown) = [a, b].iter().find(|body| !self.contains(body)) {
+            return Err(OrbitError::UnknownBody(unknown.to_string()));
+        }
+
+        let from_a: HashMap<String, usize> = self
+            .ancestors(a)
+            .into_iter()
+            .enumerate()
+            .map(|(i, v)| (v, i))
+            .collect();
+
+        // The first ancestor of b that a also orbits is the lowest common
+        // ancestor, the transfers are the hops from both sides to reach it.
+        self.ancestors(b)
+            .iter()
+            .enumerate()
+            .find_map(|(j, v)| from_a.get(v).map(|i| i + j))
+            .ok_or_else(|| OrbitError::Disconnected(a.to_owned(), b.to_owned()))
+    }
+
+    // Every body that node orbits directly or indirectly, nearest first.
+    pub fn ancestors(&self, node: &str) -> Vec<String> {
+        let mut result = vec![];
+        let mut key = node;
+        while let Some(v) = self.map.get(key) {
+            result.push(v.clone());
+            key = v;
+        }
+        result
+    }
+
+    // Number of bodies between node and the root, caching along the way.
+    fn depth(&self, node: &str, cache: &mut HashMap<String, usize>) -> usize {
+        if let Some(&d) = cache.get(node) {
+            return d;
+        }
+
+        let d = match self.map.get(node) {
+            Some(parent) => self.depth(parent, cache) + 1,
+            None => 0,
+        };
+        cache.insert(node.to_owned(), d);
+        d
+    }
+
+    // Every body with its depth from COM, sorted by depth and then by name.
+    pub fn depth_table(&self) -> Vec<(String, usize)> {
+        let mut cache = HashMap::new();
+        let mut bodies: Vec<&String> = self.map.keys().chain(self.map.values()).collect();
+        bodies.sort();
+        bodies.dedup();
+
+        let mut table: Vec<(String, usize)> = bodies
+            .into_iter()
+            .map(|b| (b.clone(), self.depth(b, &mut cache)))
+            .collect();
+        table.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+        table
+    }
+
+    // Bodies that both a and b orbit, ordered from nearest to COM.
+    pub fn common_ancestors(&self, a: &str, b: &str) -> Vec<String> {
+        let other = self.ancestors(b);
+        self.ancestors(a)
+            .into_iter()
+            .filter(|v| other.contains(v))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_test_input() -> Vec<Entry> {
+        let input = "COM)B
+B)C
+C)D
+D)E
+E)F
+B)G
+G)H
+D)I
+E)J
+J)K
+K)L
+K)YOU
+I)SAN";
+        parse_entries(input).unwrap()
+    }
+
+    #[test]
+    fn test_transfers() {
+        let graph = OrbitGraph::new(get_test_input());
+        assert_eq!(graph.transfers("YOU", "SAN"), Ok(4));
+        assert_eq!(graph.transfers("SAN", "YOU"), Ok(4));
+        assert_eq!(graph.transfers("H", "L"), Ok(6));
+        assert_eq!(
+            graph.transfers("YOU", "NOBODY"),
+            Err(OrbitError::UnknownBody("NOBODY".to_owned()))
+        );
+
+        let graph = OrbitGraph::new(parse_entries("A)B\nC)D").unwrap());
+        assert_eq!(
+            graph.transfers("B", "D"),
+            Err(OrbitError::Disconnected("B".to_owned(), "D".to_owned()))
+        );
+    }
+
+    #[test]
+    fn test_parse_entries() {
+        assert_eq!(
+            parse_entries("COM)B\nB").err(),
+            Some(OrbitError::MalformedEntry("B".to_owned()))
+        );
+        assert!(parse_entries("COM)B)C").is_err());
+        assert!(parse_entries("COM)").is_err());
+    }
+
+    #[test]
+    fn test_common_ancestors() {
+        let map = OrbitGraph::new(get_test_input());
+        assert_eq!(
+            map.common_ancestors("YOU", "SAN"),
+            vec!["D", "C", "B", "COM"]
+        );
+        assert_eq!(map.common_ancestors("H", "L"), vec!["B", "COM"]);
+    }
+
+    #[test]
+    fn test_depth_table() {
+        let table = OrbitGraph::new(get_test_input()).depth_table();
+        assert_eq!(table.len(), 14);
+        assert_eq!(table[0], ("COM".to_owned(), 0));
+        assert_eq!(table[1], ("B".to_owned(), 1));
+        assert_eq!(table[12], ("L".to_owned(), 7));
+        assert_eq!(table[13], ("YOU".to_owned(), 7));
+    }
+
+    #[test]
+    fn test_simple_orbits() {
+        assert_eq!(54, OrbitGraph::new(get_test_input()).total_orbits());
+    }
+}
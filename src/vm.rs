@@ -1,7 +1,54 @@
-use std::collections::LinkedList;
+use std::collections::{BTreeSet, LinkedList};
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead, Write};
+use std::ops::Range;
+use std::sync::mpsc::{Receiver, Sender};
+use std::time::{Duration, Instant};
 
-#[derive(Debug, PartialEq)]
-enum Opcode {
+mod amplifier;
+mod ascii;
+mod builder;
+mod decode;
+mod diff;
+mod fixture;
+#[cfg(feature = "fuzz")]
+mod fuzz;
+mod network;
+mod normalize;
+mod pool;
+mod testing;
+mod trace;
+
+pub use self::amplifier::{
+    amplifier_step_counts, best_phase_canonical, chain_output, feedback_output,
+    run_amplifier_chain, try_chain_output, try_feedback_output, validate_phases, PhaseError,
+};
+pub use self::ascii::{ascii_lines, find_ascii_strings};
+pub use self::builder::ProgramBuilder;
+pub use self::decode::{
+    decode_all, decode_at, disassemble, disassemble_annotated, instruction_size_histogram,
+    jump_graph, requires_input, DecodedInstruction,
+};
+pub use self::diff::{format_memory_diff, memory_diff};
+pub use self::fixture::load_case;
+#[cfg(feature = "fuzz")]
+pub use self::fuzz::random_program;
+pub use self::network::{Network, Packet};
+pub use self::normalize::normalize_addresses;
+pub use self::pool::StatePool;
+pub use self::testing::{
+    assert_chain_feedback_agree, assert_deterministic, assert_memory_equivalent, assert_no_output,
+    assert_pure,
+};
+pub use self::trace::{first_trace_divergence, TraceEntry, TraceHook};
+
+// Addressable memory cells unless set_max_memory says otherwise. Intcode
+// puzzles stay far below this, a higher address is almost always a bug.
+pub const DEFAULT_MAX_MEMORY: usize = 1 << 20;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Opcode {
     Add,
     Multiply,
     Input,
@@ -22,23 +69,27 @@ pub enum Mode {
 }
 
 impl Mode {
-    fn parse(m: i64, index: i64) -> Mode {
-        match index {
-            0 => Mode::from((m % 1000) / 100),
-            1 => Mode::from((m % 10_000) / 1_000),
-            2 => Mode::from((m % 100_000) / 10_000),
-            x => panic!("Unexpected index for mode: {}", x),
+    // Mode of operand index of the instruction code, None for bad modes.
+    fn try_parse(m: i64, index: usize) -> Option<Mode> {
+        let divisor = 10_i64.pow(index as u32 + 2);
+        Mode::decode((m / divisor) % 10)
+    }
+
+    fn decode(v: i64) -> Option<Mode> {
+        match v {
+            0 => Some(Mode::Position),
+            1 => Some(Mode::Immediate),
+            2 => Some(Mode::Relative),
+            _ => None,
         }
     }
 }
 
 impl From<i64> for Mode {
     fn from(v: i64) -> Self {
-        match v {
-            0 => Mode::Position,
-            1 => Mode::Immediate,
-            2 => Mode::Relative,
-            x => panic!("Unexpected mode: {}", x),
+        match Mode::decode(v) {
+            Some(m) => m,
+            None => panic!("Unexpected mode: {}", v),
         }
     }
 }
@@ -55,20 +106,60 @@ impl Operand {
     }
 }
 
+impl Opcode {
+    pub const ALL: [Opcode; 10] = [
+        Opcode::Add,
+        Opcode::Multiply,
+        Opcode::Input,
+        Opcode::Output,
+        Opcode::JumpIfTrue,
+        Opcode::JumpIfFalse,
+        Opcode::LessThan,
+        Opcode::Equals,
+        Opcode::AdjustRelativeBase,
+        Opcode::Halt,
+    ];
+
+    // Position in Opcode::ALL.
+    fn index(self) -> usize {
+        match self {
+            Opcode::Halt => 9,
+            op => i64::from(op) as usize - 1,
+        }
+    }
+
+    fn decode(v: i64) -> Option<Opcode> {
+        match v % 100 {
+            1 => Some(Opcode::Add),
+            2 => Some(Opcode::Multiply),
+            3 => Some(Opcode::Input),
+            4 => Some(Opcode::Output),
+            5 => Some(Opcode::JumpIfTrue),
+            6 => Some(Opcode::JumpIfFalse),
+            7 => Some(Opcode::LessThan),
+            8 => Some(Opcode::Equals),
+            9 => Some(Opcode::AdjustRelativeBase),
+            99 => Some(Opcode::Halt),
+            _ => None,
+        }
+    }
+
+    // Number of operands that follow the opcode in memory.
+    fn operand_count(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => 3,
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => 2,
+            Opcode::Input | Opcode::Output | Opcode::AdjustRelativeBase => 1,
+            Opcode::Halt => 0,
+        }
+    }
+}
+
 impl From<i64> for Opcode {
     fn from(v: i64) -> Self {
-        match v % 100 {
-            1 => Opcode::Add,
-            2 => Opcode::Multiply,
-            3 => Opcode::Input,
-            4 => Opcode::Output,
-            5 => Opcode::JumpIfTrue,
-            6 => Opcode::JumpIfFalse,
-            7 => Opcode::LessThan,
-            8 => Opcode::Equals,
-            9 => Opcode::AdjustRelativeBase,
-            99 => Opcode::Halt,
-            x => panic!("Unexpected opcode: {}", x),
+        match Opcode::decode(v) {
+            Some(op) => op,
+            None => panic!("Unexpected opcode: {}", v % 100),
         }
     }
 }
@@ -96,399 +187,1285 @@ pub struct Instruction {
     pub operands: Vec<Operand>,
 }
 
+#[derive(Debug, PartialEq)]
+pub enum VmError {
+    // The program wrote to memory more times than allowed by set_max_writes.
+    WriteLimitExceeded,
+    // The value at pc isn't a known opcode.
+    InvalidOpcode { pc: usize, value: i64 },
+    // The instruction at pc has a parameter mode that isn't 0, 1 or 2.
+    InvalidMode { pc: usize, value: i64 },
+    // The program ends before all operands of the instruction at pc.
+    MissingOperands { pc: usize },
+    // Only ASCII characters can be fed to ASCII programs.
+    NonAsciiInput(char),
+    // A computed address is negative or past the limit set by set_max_memory.
+    BadAddress(i64),
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VmError::WriteLimitExceeded => write!(f, "memory write limit exceeded"),
+            VmError::InvalidOpcode { pc, value } => {
+                write!(f, "invalid opcode {} at address {}", value, pc)
+            }
+            VmError::InvalidMode { pc, value } => {
+                write!(f, "invalid parameter mode in {} at address {}", value, pc)
+            }
+            VmError::MissingOperands { pc } => {
+                write!(f, "missing operands for instruction at address {}", pc)
+            }
+            VmError::NonAsciiInput(c) => write!(f, "input {:?} is not an ASCII character", c),
+            VmError::BadAddress(a) => write!(f, "bad memory address {}", a),
+        }
+    }
+}
+
+impl Error for VmError {}
+
+#[derive(Debug, PartialEq)]
+pub enum DiagnosticError {
+    NoOutput,
+    // Position and value of every non-zero test result before the code.
+    FailedTests(Vec<(usize, i64)>),
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SingleOutputError {
+    NoOutput,
+    // Every output that was produced.
+    MultipleOutputs(Vec<i64>),
+    // The program stopped on an error before halting.
+    Vm(VmError),
+}
+
+impl From<VmError> for SingleOutputError {
+    fn from(e: VmError) -> Self {
+        SingleOutputError::Vm(e)
+    }
+}
+
+impl fmt::Display for SingleOutputError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SingleOutputError::NoOutput => write!(f, "program produced no output"),
+            SingleOutputError::MultipleOutputs(v) => {
+                write!(f, "expected a single output but got {:?}", v)
+            }
+            SingleOutputError::Vm(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl Error for SingleOutputError {}
+
+// Interprets day 5 output: the last value is the diagnostic code if every
+// value before it is zero, otherwise the (index, value) of every failed test.
+// An empty output has no code and no failed tests.
+pub fn diagnostic_result(outputs: &[i64]) -> Result<i64, Vec<(usize, i64)>> {
+    let (code, tests) = match outputs.split_last() {
+        Some(v) => v,
+        None => return Err(vec![]),
+    };
+
+    let failed: Vec<(usize, i64)> = tests
+        .iter()
+        .enumerate()
+        .filter(|(_, &v)| v != 0)
+        .map(|(i, &v)| (i, v))
+        .collect();
+
+    if failed.is_empty() {
+        Ok(*code)
+    } else {
+        Err(failed)
+    }
+}
+
+// A single memory write as (address, old value, new value).
+pub type MemoryDelta = (usize, i64, i64);
+
+// Result of executing a single instruction.
+#[derive(Debug, PartialEq)]
+pub enum StepResult {
+    Continued,
+    Output(i64),
+    WaitingForInput,
+    Halted,
+}
+
+// Why the debugger stopped running the VM.
+#[derive(Debug, PartialEq)]
+pub enum RunState {
+    // Stopped after the requested number of instructions, can be resumed.
+    Paused,
+    Halted,
+    WaitingForInput,
+    // About to execute the instruction at this address.
+    Breakpoint(usize),
+    // The instruction just executed wrote to this address.
+    Watchpoint(usize),
+    // Ran out of wall-clock time, can be resumed.
+    Timeout,
+}
+
+// What the VM does when pc points at a value that isn't a known opcode.
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub enum UnknownOpcodePolicy {
+    Panic,
+    // Stop with VmError::InvalidOpcode.
+    #[default]
+    Error,
+    // Treat it as a one cell instruction that does nothing.
+    SkipAsNop,
+}
+
+// An entry of the interleaved I/O log.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum IoEvent {
+    Input(i64),
+    Output(i64),
+}
+
+// Suspicious uses of the relative base. Both are legal, but usually point at
+// a bug in relative mode handling.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RelativeBaseWarning {
+    // The instruction at pc left the relative base negative.
+    NegativeBase { pc: usize, base: i64 },
+    // The instruction at pc has a relative operand that resolves below 0.
+    NegativeAddress { pc: usize, address: i64 },
+}
+
+// What a finished run produced.
+#[derive(Debug, PartialEq)]
+pub struct RunResult {
+    pub outputs: Vec<i64>,
+    pub memory: Vec<i64>,
+    pub steps: u64,
+}
+
+// Everything needed to resume execution from a given point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct VmState {
+    pub bytecode: Vec<i64>,
+    pub pc: usize,
+    pub relative_base: i64,
+    pub inputs: Vec<i64>,
+    pub outputs: Vec<i64>,
+    pub done: bool,
+}
+
+// Execution state saved right before an Input instruction consumed a value.
+// Memory isn't copied, instead every write since records the value it
+// overwrote.
 #[derive(Debug)]
+struct InputCheckpoint {
+    pc: usize,
+    relative_base: i64,
+    outputs_len: usize,
+    writes: u64,
+    high_water_mark: usize,
+    // Address and previous value of every write since, oldest first.
+    undo: Vec<(usize, i64)>,
+}
+
 pub struct VM {
     bytecode: Vec<i64>,
     pc: usize,
     inputs: LinkedList<i64>,
     outputs: Vec<i64>,
     done: bool,
+    // The last step stopped on an Input with nothing queued.
+    waiting: bool,
     relative_base: i64,
+    // Instructions executed so far.
+    steps: u64,
+    // Executed instructions per opcode, indexed like Opcode::ALL.
+    opcode_counts: [u64; 10],
+    writes: u64,
+    max_writes: Option<u64>,
+    // Addresses at or above this are reported as VmError::BadAddress.
+    max_memory: usize,
+    high_water_mark: usize,
+    // Length of the program that was loaded, memory past it starts as 0.
+    loaded_len: usize,
+    last_write: Option<usize>,
+    // The breakpoint resume last stopped at, skipped once by the next resume.
+    last_break: Option<usize>,
+    breakpoints: BTreeSet<usize>,
+    watchpoints: BTreeSet<usize>,
+    // Memory changes of the current instruction when recording is enabled.
+    deltas: Option<Vec<MemoryDelta>>,
+    output_callback: Option<Box<dyn FnMut(i64) + Send>>,
+    unknown_opcode_policy: UnknownOpcodePolicy,
+    trace: Option<Vec<TraceEntry>>,
+    // Output count at each run_till_output return, while recording.
+    segment_ends: Option<Vec<usize>>,
+    // Only instructions in this pc range are traced.
+    trace_filter: Option<Range<usize>>,
+    // Called with pc and the instruction before it executes.
+    trace_hook: Option<TraceHook>,
+    checkpoint_inputs: bool,
+    // Consumed inputs and outputs in order, while echoing is enabled.
+    io_log: Option<Vec<IoEvent>>,
+    input_checkpoint: Option<InputCheckpoint>,
+    // Logged while relative base checks are enabled.
+    rb_warnings: Option<Vec<RelativeBaseWarning>>,
+    // Blocked on when an Input finds nothing queued.
+    input_channel: Option<Receiver<i64>>,
+    // Every output is also sent here.
+    output_channel: Option<Sender<i64>>,
+}
+
+impl fmt::Debug for VM {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VM")
+            .field("bytecode", &self.bytecode)
+            .field("pc", &self.pc)
+            .field("inputs", &self.inputs)
+            .field("outputs", &self.outputs)
+            .field("done", &self.done)
+            .field("waiting", &self.waiting)
+            .field("relative_base", &self.relative_base)
+            .field("steps", &self.steps)
+            .field("opcode_counts", &self.opcode_counts)
+            .field("writes", &self.writes)
+            .field("max_writes", &self.max_writes)
+            .field("max_memory", &self.max_memory)
+            .field("high_water_mark", &self.high_water_mark)
+            .field("loaded_len", &self.loaded_len)
+            .field("breakpoints", &self.breakpoints)
+            .field("watchpoints", &self.watchpoints)
+            .field("unknown_opcode_policy", &self.unknown_opcode_policy)
+            .field("checkpoint_inputs", &self.checkpoint_inputs)
+            .field("io_log", &self.io_log)
+            .field("rb_warnings", &self.rb_warnings)
+            .field("input_channel", &self.input_channel.is_some())
+            .field("output_channel", &self.output_channel.is_some())
+            .finish()
+    }
 }
 
 impl VM {
     pub fn new(bytecode: Vec<i64>) -> VM {
+        let loaded_len = bytecode.len();
         VM {
             bytecode,
             pc: 0,
             inputs: LinkedList::new(),
             outputs: vec![],
             done: false,
+            waiting: false,
             relative_base: 0,
+            steps: 0,
+            opcode_counts: [0; 10],
+            writes: 0,
+            max_writes: None,
+            max_memory: DEFAULT_MAX_MEMORY,
+            high_water_mark: 0,
+            loaded_len,
+            last_write: None,
+            last_break: None,
+            breakpoints: BTreeSet::new(),
+            watchpoints: BTreeSet::new(),
+            deltas: None,
+            output_callback: None,
+            unknown_opcode_policy: UnknownOpcodePolicy::default(),
+            trace: None,
+            segment_ends: None,
+            trace_hook: None,
+            trace_filter: None,
+            checkpoint_inputs: false,
+            io_log: None,
+            rb_warnings: None,
+            input_channel: None,
+            output_channel: None,
+            input_checkpoint: None,
         }
     }
 
+    // Chaining form of set_inputs, e.g. VM::new(p).with_inputs(&[1, 2]).
+    pub fn with_inputs(mut self, v: &[i64]) -> Self {
+        self.set_inputs(v);
+        self
+    }
+
+    pub fn with_input(mut self, v: i64) -> Self {
+        self.inputs.push_back(v);
+        self
+    }
+
     pub fn set_inputs(&mut self, v: &[i64]) {
         for &i in v {
             self.inputs.push_back(i);
         }
     }
 
+    // Registers a callback that is invoked with every output as soon as it
+    // is produced. Outputs are still buffered as usual. The callback has to
+    // be Send so that a VM can still be moved to another thread.
+    pub fn set_output_callback(&mut self, cb: impl FnMut(i64) + Send + 'static) {
+        self.output_callback = Some(Box::new(cb));
+    }
+
+    // Makes an Input with nothing queued block until a value arrives on rx.
+    // If the sender hangs up the VM waits for input as usual.
+    pub fn set_input_channel(&mut self, rx: Receiver<i64>) {
+        self.input_channel = Some(rx);
+    }
+
+    // Sends every output to tx as well as buffering it.
+    pub fn set_output_channel(&mut self, tx: Sender<i64>) {
+        self.output_channel = Some(tx);
+    }
+
+    // Records every consumed input along with the outputs in io_log.
+    pub fn set_echo_input(&mut self, echo: bool) {
+        if !echo {
+            self.io_log = None;
+        } else if self.io_log.is_none() {
+            self.io_log = Some(vec![]);
+        }
+    }
+
+    pub fn io_log(&self) -> &[IoEvent] {
+        self.io_log.as_deref().unwrap_or(&[])
+    }
+
+    // Starts logging RelativeBaseWarnings. Execution isn't affected.
+    pub fn enable_relative_base_checks(&mut self) {
+        if self.rb_warnings.is_none() {
+            self.rb_warnings = Some(vec![]);
+        }
+    }
+
+    pub fn relative_base_warnings(&self) -> &[RelativeBaseWarning] {
+        self.rb_warnings.as_deref().unwrap_or(&[])
+    }
+
     fn output(&mut self, o: i64) {
         self.outputs.push(o);
+        if let Some(log) = self.io_log.as_mut() {
+            log.push(IoEvent::Output(o));
+        }
+        if let Some(cb) = self.output_callback.as_mut() {
+            cb(o);
+        }
+        if let Some(tx) = self.output_channel.as_ref() {
+            // Nobody listening anymore isn't an error for the program.
+            let _ = tx.send(o);
+        }
     }
 
     pub fn outputs(&self) -> Vec<i64> {
         self.outputs.clone()
     }
 
+    pub fn outputs_slice(&self) -> &[i64] {
+        &self.outputs
+    }
+
+    // Moves the buffered outputs out, leaving the buffer empty.
+    pub fn drain_outputs(&mut self) -> Vec<i64> {
+        std::mem::take(&mut self.outputs)
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+
+    // True if the VM stopped on an Input instruction because no input was
+    // queued. Queue more input and run again to resume.
+    pub fn is_waiting(&self) -> bool {
+        self.waiting
+    }
+
     pub fn get_last_output(&self) -> i64 {
-        *self.outputs.last().unwrap()
+        self.try_last_output()
+            .expect("VM hasn't produced any output yet")
     }
 
-    pub fn run(&mut self) {
-        while !self.done {
-            self.run_till_output();
+    pub fn try_last_output(&self) -> Option<i64> {
+        self.outputs.last().cloned()
+    }
+
+    // Day 5 style programs output a zero for every passing test followed by
+    // the diagnostic code. Returns the code only if every test passed.
+    pub fn diagnostic_code(&self) -> Result<i64, DiagnosticError> {
+        if self.outputs.is_empty() {
+            return Err(DiagnosticError::NoOutput);
         }
+        diagnostic_result(&self.outputs).map_err(DiagnosticError::FailedTests)
     }
 
-    // Executes the VM.
-    pub fn run_till_output(&mut self) {
-        if self.done {
-            return;
+    // Runs until the program halts or waits for input and returns the
+    // outputs, memory and instruction count in one go.
+    pub fn run_to_completion(&mut self) -> Result<RunResult, VmError> {
+        self.run()?;
+        Ok(RunResult {
+            outputs: self.outputs.clone(),
+            memory: self.bytecode.clone(),
+            steps: self.steps,
+        })
+    }
+
+    // Runs to completion for programs like day 9 that are expected to
+    // produce exactly one output.
+    pub fn run_single_output(&mut self) -> Result<i64, SingleOutputError> {
+        self.run()?;
+        match self.outputs.as_slice() {
+            [] => Err(SingleOutputError::NoOutput),
+            [v] => Ok(*v),
+            v => Err(SingleOutputError::MultipleOutputs(v.to_vec())),
         }
+    }
 
-        loop {
-            let inst = self.get_next_instruction();
-            match Opcode::from(inst.opcode) {
-                Opcode::Halt => {
-                    self.pc += 1;
-                    self.done = true;
-                    break;
-                }
-                Opcode::AdjustRelativeBase => {
-                    let value = self.get_value(&inst.operands[0]);
-                    self.relative_base += value;
-                    self.pc += 2;
-                }
-                Opcode::Add => {
-                    let v1 = self.get_value(&inst.operands[0]);
-                    let v2 = self.get_value(&inst.operands[1]);
-
-                    // Parameters that an instruction writes to
-                    // are always positional.
-                    let dest = self.get_absolute_address(&inst.operands[2]);
-                    self.set_mem(dest, v1 + v2);
-                    self.pc += 4;
-                }
-                Opcode::Multiply => {
-                    let v1 = self.get_value(&inst.operands[0]);
-                    let v2 = self.get_value(&inst.operands[1]);
-
-                    // Parameters that an instruction writes to
-                    // are always positional.
-                    let dest = self.get_absolute_address(&inst.operands[2]);
-                    self.set_mem(dest, v1 * v2);
-                    self.pc += 4;
-                }
-                Opcode::Input => {
-                    let inp = self.inputs.pop_front().unwrap();
-                    // In case of input, we only care about the address where to
-                    // store the value.
-                    let mut address = inst.operands[0].value;
-                    if inst.operands[0].mode == Mode::Relative {
-                        address += self.relative_base;
-                    }
-                    self.set_mem(address as usize, inp);
-                    self.pc += 2;
-                }
-                Opcode::Output => {
-                    let value = self.get_value(&inst.operands[0]);
-                    self.output(value);
-                    self.pc += 2;
-                    // We break out to let the caller consume output for
-                    // the feedback loop.
-                    break;
-                }
-                Opcode::JumpIfTrue => {
-                    if self.get_value(&inst.operands[0]) != 0 {
-                        self.pc = self.get_value(&inst.operands[1]) as usize;
-                    } else {
-                        self.pc += 3;
-                    }
-                }
-                Opcode::JumpIfFalse => {
-                    if self.get_value(&inst.operands[0]) == 0 {
-                        self.pc = self.get_value(&inst.operands[1]) as usize;
-                    } else {
-                        self.pc += 3;
-                    }
-                }
-                Opcode::LessThan => {
-                    let v1 = self.get_value(&inst.operands[0]);
-                    let v2 = self.get_value(&inst.operands[1]);
+    pub fn instruction_count(&self) -> u64 {
+        self.steps
+    }
 
-                    let mut result = 0;
-                    if v1 < v2 {
-                        result = 1
-                    }
-                    // Parameters that an instruction writes to
-                    // are always positional.
-                    let address = self.get_absolute_address(&inst.operands[2]);
-                    self.set_mem(address, result);
-                    self.pc += 4;
-                }
-                Opcode::Equals => {
-                    let v1 = self.get_value(&inst.operands[0]);
-                    let v2 = self.get_value(&inst.operands[1]);
+    // How many times each opcode was executed, in Opcode::ALL order.
+    // Opcodes that never ran are left out.
+    pub fn opcode_counts(&self) -> Vec<(Opcode, u64)> {
+        Opcode::ALL
+            .iter()
+            .map(|&op| (op, self.opcode_counts[op.index()]))
+            .filter(|&(_, n)| n > 0)
+            .collect()
+    }
 
-                    let mut result = 1;
-                    if v1 != v2 {
-                        result = 0;
-                    }
-                    // Parameters that an instruction writes to
-                    // are always positional.
-                    let address = self.get_absolute_address(&inst.operands[2]);
-                    self.set_mem(address, result);
-                    self.pc += 4;
-                }
+    // Compact progress line like "pc=1234 rb=0 steps=1.2M out=57", meant to
+    // be redrawn in place with \r.
+    pub fn status_line(&self) -> String {
+        let steps = match self.steps {
+            n if n < 1_000 => n.to_string(),
+            n if n < 1_000_000 => format!("{:.1}K", n as f64 / 1e3),
+            n if n < 1_000_000_000 => format!("{:.1}M", n as f64 / 1e6),
+            n => format!("{:.1}G", n as f64 / 1e9),
+        };
+
+        format!(
+            "pc={} rb={} steps={} out={}",
+            self.pc,
+            self.relative_base,
+            steps,
+            self.outputs.len()
+        )
+    }
+
+    // Caps the number of memory writes so that runaway self-modifying
+    // loops are reported as VmError::WriteLimitExceeded.
+    pub fn set_max_writes(&mut self, n: u64) {
+        self.max_writes = Some(n);
+    }
+
+    // Caps the addressable memory so that a runaway address is reported as
+    // VmError::BadAddress instead of growing memory until the process runs
+    // out of it. Defaults to DEFAULT_MAX_MEMORY cells.
+    pub fn set_max_memory(&mut self, n: usize) {
+        self.max_memory = n;
+    }
+
+    pub fn set_unknown_opcode_policy(&mut self, policy: UnknownOpcodePolicy) {
+        self.unknown_opcode_policy = policy;
+    }
+
+    // Runs until the program halts or waits for input. Invalid instructions
+    // and addresses are reported as a VmError, see UnknownOpcodePolicy::Panic
+    // to panic on bad opcodes instead.
+    pub fn run(&mut self) -> Result<(), VmError> {
+        while !self.done {
+            self.run_till_stop()?;
+            if self.waiting {
+                break;
             }
         }
+        Ok(())
     }
 
-    fn get_absolute_address(&self, op: &Operand) -> usize {
-        match op.mode {
-            Mode::Position => op.value as usize,
-            Mode::Relative => (op.value + self.relative_base) as usize,
-            Mode::Immediate => panic!("Invalid mode for operand: {:?}", op),
-        }
+    // Returns true if the VM is blocked on an Input instruction with
+    // nothing queued.
+    fn needs_input(&self) -> bool {
+        !self.done
+            && self.inputs.is_empty()
+            && Opcode::decode(self.read_mem(self.pc)) == Some(Opcode::Input)
     }
 
-    fn set_mem(&mut self, address: usize, v: i64) {
-        self.ensure_mem_availability(address);
-        self.bytecode[address] = v;
+    // Runs the VM with integer I/O on stdin/stdout, one value per line.
+    pub fn run_stdio_ints(&mut self) -> io::Result<()> {
+        let stdin = io::stdin();
+        self.run_ints_with(stdin.lock(), io::stdout())
     }
 
-    fn get_value(&mut self, op: &Operand) -> i64 {
-        match op.mode {
-            Mode::Immediate => op.value,
-            Mode::Position => {
-                let address = op.value as usize;
-                self.ensure_mem_availability(address);
-                self.bytecode[address]
+    // Reads one integer per line from input whenever the program needs one
+    // and writes each output on its own line. Stops when the program halts
+    // or the input is exhausted. A VmError is returned as InvalidData.
+    pub fn run_ints_with<R: BufRead, W: Write>(
+        &mut self,
+        mut input: R,
+        mut output: W,
+    ) -> io::Result<()> {
+        while !self.done {
+            if self.needs_input() {
+                let mut line = String::new();
+                if input.read_line(&mut line)? == 0 {
+                    return Ok(());
+                }
+
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+
+                let v = line
+                    .parse::<i64>()
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                self.inputs.push_back(v);
             }
-            Mode::Relative => {
-                let address = op.value + self.relative_base;
-                self.ensure_mem_availability(address as usize);
-                self.bytecode[address as usize]
+
+            let before = self.outputs.len();
+            self.run_till_output()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            for v in &self.outputs[before..] {
+                writeln!(output, "{}", v)?;
             }
         }
+
+        Ok(())
     }
 
-    fn ensure_mem_availability(&mut self, mem_size: usize) {
-        if mem_size > self.bytecode.len() {
-            // double the memory
-            self.bytecode.resize(mem_size * 2, 0);
-        }
+    pub fn add_breakpoint(&mut self, address: usize) {
+        self.breakpoints.insert(address);
+    }
+
+    pub fn remove_breakpoint(&mut self, address: usize) {
+        self.breakpoints.remove(&address);
     }
 
-    fn get_next_instruction(&mut self) -> Instruction {
-        let mut operands: Vec<Operand> = Vec::new();
+    // Watchpoints fire after an instruction writes to the address.
+    pub fn add_watchpoint(&mut self, address: usize) {
+        self.watchpoints.insert(address);
+    }
 
-        let code = self.bytecode[self.pc];
-        let mode = code - (code % 100);
-        let opcode = code % 100;
+    pub fn remove_watchpoint(&mut self, address: usize) {
+        self.watchpoints.remove(&address);
+    }
 
-        match Opcode::from(code) {
-            Opcode::Add => {
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 1],
-                    Mode::from(Mode::parse(mode, 0)),
-                ));
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 2],
-                    Mode::from(Mode::parse(mode, 1)),
-                ));
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 3],
-                    Mode::from(Mode::parse(mode, 2)),
-                ));
-            }
-            Opcode::Multiply => {
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 1],
-                    Mode::from(Mode::parse(mode, 0)),
-                ));
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 2],
-                    Mode::from(Mode::parse(mode, 1)),
-                ));
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 3],
-                    Mode::from(Mode::parse(mode, 2)),
-                ));
+    // Runs until the program halts, blocks on input or hits a breakpoint or
+    // watchpoint. The breakpoint the previous resume stopped at doesn't fire
+    // again so that resuming after a break makes progress.
+    pub fn resume(&mut self) -> Result<RunState, VmError> {
+        let mut skip = self.last_break.take();
+        loop {
+            if self.breakpoints.contains(&self.pc) && skip != Some(self.pc) {
+                self.last_break = Some(self.pc);
+                return Ok(RunState::Breakpoint(self.pc));
             }
-            Opcode::Input => {
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 1],
-                    Mode::from(Mode::parse(mode, 0)),
-                ));
+            skip = None;
+
+            match self.step()? {
+                StepResult::Halted => return Ok(RunState::Halted),
+                StepResult::WaitingForInput => return Ok(RunState::WaitingForInput),
+                StepResult::Continued | StepResult::Output(_) => (),
             }
-            Opcode::Output => {
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 1],
-                    Mode::from(Mode::parse(mode, 0)),
-                ));
+
+            if let Some(address) = self.last_write {
+                if self.watchpoints.contains(&address) {
+                    return Ok(RunState::Watchpoint(address));
+                }
             }
-            Opcode::JumpIfTrue => {
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 1],
-                    Mode::from(Mode::parse(mode, 0)),
-                ));
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 2],
-                    Mode::from(Mode::parse(mode, 1)),
-                ));
+        }
+    }
+
+    // Executes up to n instructions, stopping early if the program halts or
+    // blocks on input.
+    pub fn step_n(&mut self, n: usize) -> Result<RunState, VmError> {
+        for _ in 0..n {
+            match self.step()? {
+                StepResult::Halted => return Ok(RunState::Halted),
+                StepResult::WaitingForInput => return Ok(RunState::WaitingForInput),
+                StepResult::Continued | StepResult::Output(_) => (),
             }
-            Opcode::JumpIfFalse => {
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 1],
-                    Mode::from(Mode::parse(mode, 0)),
-                ));
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 2],
-                    Mode::from(Mode::parse(mode, 1)),
-                ));
+        }
+
+        if self.done {
+            Ok(RunState::Halted)
+        } else {
+            Ok(RunState::Paused)
+        }
+    }
+
+    // Runs until the program halts or blocks on input, giving up once dur has
+    // elapsed. The clock is only checked every TIMEOUT_CHECK_INTERVAL steps.
+    pub fn run_timeout(&mut self, dur: Duration) -> Result<RunState, VmError> {
+        const TIMEOUT_CHECK_INTERVAL: usize = 10_000;
+
+        let start = Instant::now();
+        loop {
+            match self.step_n(TIMEOUT_CHECK_INTERVAL)? {
+                RunState::Paused => (),
+                state => return Ok(state),
             }
-            Opcode::LessThan => {
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 1],
-                    Mode::from(Mode::parse(mode, 0)),
-                ));
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 2],
-                    Mode::from(Mode::parse(mode, 1)),
-                ));
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 3],
-                    Mode::from(Mode::parse(mode, 2)),
-                ));
+
+            if start.elapsed() >= dur {
+                return Ok(RunState::Timeout);
             }
-            Opcode::Equals => {
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 1],
-                    Mode::from(Mode::parse(mode, 0)),
-                ));
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 2],
-                    Mode::from(Mode::parse(mode, 1)),
-                ));
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 3],
-                    Mode::from(Mode::parse(mode, 2)),
-                ));
+        }
+    }
+
+    // Runs until max_outputs more values have been produced, the program
+    // stops or fails, or max_steps instructions have been executed, and
+    // returns the smallest period with which the second half of those outputs
+    // repeats. At least two full cycles have to be observed.
+    //
+    // max_steps is there so that a program that loops without printing can't
+    // hang the caller. This isn't Brent's algorithm: Brent needs each value to
+    // determine the next one, which an output stream like 1, 1, 2, 1, 1, 2
+    // doesn't, so the collected tail is compared against its own shifts
+    // instead. That's quadratic in max_outputs, which stays small in practice.
+    pub fn detect_output_period(&mut self, max_outputs: usize, max_steps: usize) -> Option<usize> {
+        let start = self.outputs.len();
+        for _ in 0..max_steps {
+            if self.outputs.len() - start >= max_outputs {
+                break;
             }
-            Opcode::AdjustRelativeBase => {
-                operands.push(Operand::new(
-                    self.bytecode[self.pc + 1],
-                    Mode::from(Mode::parse(mode, 0)),
-                ));
+            match self.step() {
+                Ok(StepResult::Continued) | Ok(StepResult::Output(_)) => (),
+                Ok(StepResult::Halted) | Ok(StepResult::WaitingForInput) | Err(_) => break,
             }
-            Opcode::Halt => (),
         }
 
-        Instruction { opcode, operands }
+        let seen = &self.outputs[start..];
+        let tail = &seen[seen.len() / 2..];
+        (1..=tail.len() / 2).find(|&p| (0..tail.len() - p).all(|i| tail[i] == tail[i + p]))
     }
 
-    pub fn bytecode(&self) -> Vec<i64> {
-        self.bytecode.clone()
+    // Executes a single instruction and returns its opcode along with every
+    // memory cell it wrote. The opcode is None for an unknown instruction
+    // that UnknownOpcodePolicy::SkipAsNop skipped.
+    pub fn step_with_delta(&mut self) -> Result<(Option<Opcode>, Vec<MemoryDelta>), VmError> {
+        if self.done {
+            return Ok((Some(Opcode::Halt), vec![]));
+        }
+
+        let opcode = Opcode::decode(self.read_mem(self.pc));
+        self.deltas = Some(vec![]);
+        let result = self.step();
+        let deltas = self.deltas.take().unwrap_or_default();
+        result?;
+
+        Ok((opcode, deltas))
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    // Loads a new program and clears all execution state. Breakpoints and
+    // watchpoints are kept so that a debugging session survives a restart.
+    pub fn reset(&mut self, bytecode: Vec<i64>) {
+        self.loaded_len = bytecode.len();
+        self.bytecode = bytecode;
+        self.clear_state();
+    }
 
-    #[test]
-    fn test_parse_mode() {
-        let m = 1002;
-        assert_eq!(Mode::Position, Mode::parse(m, 0));
-        assert_eq!(Mode::Immediate, Mode::parse(m, 1));
-        assert_eq!(Mode::Position, Mode::parse(m, 2));
+    // Same as reset but reuses the existing memory. Memory keeps its grown
+    // size and only the cells that can be non-zero, the previous program
+    // and everything up to the high-water mark, are cleared.
+    pub fn reset_to(&mut self, original: &[i64]) {
+        if original.len() >= self.bytecode.len() {
+            self.bytecode.clear();
+            self.bytecode.extend_from_slice(original);
+        } else {
+            let dirty = self
+                .loaded_len
+                .max(self.high_water_mark + 1)
+                .min(self.bytecode.len());
+            self.bytecode[..original.len()].copy_from_slice(original);
+            for v in self.bytecode[original.len()..dirty.max(original.len())].iter_mut() {
+                *v = 0;
+            }
+        }
 
-        let m = 11100;
-        assert_eq!(Mode::Immediate, Mode::parse(m, 0));
-        assert_eq!(Mode::Immediate, Mode::parse(m, 1));
-        assert_eq!(Mode::Immediate, Mode::parse(m, 2));
+        self.loaded_len = original.len();
+        self.clear_state();
     }
 
-    #[test]
-    fn test_add() {
-        let program = vec![1, 0, 0, 0, 99];
-        let expected = vec![2, 0, 0, 0, 99];
+    // Like reset_to but also drops all breakpoints and watchpoints.
+    pub fn reset_full(&mut self, original: &[i64]) {
+        self.reset_to(original);
+        self.breakpoints.clear();
+        self.watchpoints.clear();
+    }
 
-        let mut vm = VM::new(program);
-        vm.run();
-        assert_eq!(vm.bytecode, expected);
-        assert_eq!(5, vm.pc);
+    fn clear_state(&mut self) {
+        self.pc = 0;
+        self.inputs.clear();
+        self.outputs.clear();
+        self.done = false;
+        self.waiting = false;
+        self.relative_base = 0;
+        self.steps = 0;
+        self.opcode_counts = [0; 10];
+        self.writes = 0;
+        self.high_water_mark = 0;
+        self.last_write = None;
+        self.last_break = None;
+        if let Some(trace) = self.trace.as_mut() {
+            trace.clear();
+        }
+        if let Some(ends) = self.segment_ends.as_mut() {
+            ends.clear();
+        }
+        if let Some(warnings) = self.rb_warnings.as_mut() {
+            warnings.clear();
+        }
+        self.input_checkpoint = None;
+        if let Some(log) = self.io_log.as_mut() {
+            log.clear();
+        }
     }
 
-    fn test_multiply() {
-        let program = vec![2, 3, 0, 3, 99];
-        let expected = vec![2, 3, 0, 6, 99];
+    // From now on, remember the state right before every Input consumes a
+    // value so that restore_input_checkpoint can roll back to the latest one.
+    pub fn checkpoint_at_input(&mut self) {
+        self.checkpoint_inputs = true;
+    }
 
-        let mut vm = VM::new(program);
-        vm.run();
-        assert_eq!(vm.bytecode, expected);
-        assert_eq!(5, vm.pc);
+    // Rolls back to the latest input checkpoint. The VM is left on the Input
+    // instruction without the value it consumed, so the next queued input is
+    // read instead. Returns false if no input has been consumed yet.
+    pub fn restore_input_checkpoint(&mut self) -> bool {
+        let c = match self.input_checkpoint.as_mut() {
+            Some(c) => c,
+            None => return false,
+        };
+
+        for (address, v) in c.undo.drain(..).rev() {
+            self.bytecode[address] = v;
+        }
+        self.pc = c.pc;
+        self.relative_base = c.relative_base;
+        self.outputs.truncate(c.outputs_len);
+        self.writes = c.writes;
+        self.high_water_mark = c.high_water_mark;
+        self.done = false;
+        self.waiting = false;
+        self.last_write = None;
+        self.last_break = None;
+        true
     }
 
-    fn test_simple_program() {
-        let program = vec![2, 4, 4, 5, 99, 0];
-        let expected = vec![2, 4, 4, 5, 99, 9801];
+    pub fn snapshot(&self) -> VmState {
+        VmState {
+            bytecode: self.bytecode.clone(),
+            pc: self.pc,
+            relative_base: self.relative_base,
+            inputs: self.inputs.iter().cloned().collect(),
+            outputs: self.outputs.clone(),
+            done: self.done,
+        }
+    }
 
-        let mut vm = VM::new(program);
-        vm.run();
-        assert_eq!(vm.bytecode, expected);
-        assert_eq!(5, vm.pc);
+    // Rolls back to a snapshot. Debugging setup like breakpoints, tracing
+    // and limits is left alone.
+    pub fn restore(&mut self, state: VmState) {
+        // Any cell of the restored memory may be non-zero.
+        self.loaded_len = state.bytecode.len();
+        self.bytecode = state.bytecode;
+        self.pc = state.pc;
+        self.relative_base = state.relative_base;
+        self.inputs = state.inputs.into_iter().collect();
+        self.outputs = state.outputs;
+        self.done = state.done;
+        self.waiting = false;
+        self.last_write = None;
+        self.last_break = None;
+        // The undo log doesn't apply to the restored memory.
+        self.input_checkpoint = None;
     }
 
-    fn test_simple_program2() {
-        let program = vec![1, 1, 1, 4, 99, 5, 6, 0, 99];
-        let expected = vec![30, 1, 1, 4, 2, 5, 6, 0, 99];
+    // Runs until the next output or until the program halts or waits for
+    // input.
+    pub fn run_till_output(&mut self) -> Result<(), VmError> {
+        self.run_till_stop()?;
+        if let Some(ends) = self.segment_ends.as_mut() {
+            ends.push(self.outputs.len());
+        }
+        Ok(())
+    }
 
-        let mut vm = VM::new(program);
-        vm.run();
-        assert_eq!(vm.bytecode, expected);
-        assert_eq!(9, vm.pc);
+    fn run_till_stop(&mut self) -> Result<(), VmError> {
+        // We break out on output to let the caller consume it for the
+        // feedback loop.
+        while let StepResult::Continued = self.step()? {}
+        Ok(())
     }
 
-    #[test]
-    fn test_input_output() {
-        let program = vec![1, 1, 1, 4, 99, 5, 6, 0, 3, 0, 4, 0, 99];
-        let expected = vec![99, 1, 1, 4, 2, 5, 6, 0, 3, 0, 4, 0, 99];
-        let mut vm = VM::new(program);
-        vm.set_inputs(&[99]);
-        vm.run();
-        assert_eq!(vm.bytecode, expected);
-        assert_eq!(vm.outputs, vec![99]);
+    // Starts recording which outputs each run_till_output call produced.
+    pub fn record_output_segments(&mut self) {
+        if self.segment_ends.is_none() {
+            self.segment_ends = Some(vec![]);
+        }
     }
 
-    #[test]
-    fn test_jump_instructions() {
+    // Ranges of outputs() produced by each recorded run_till_output call.
+    pub fn output_segments(&self) -> Vec<Range<usize>> {
+        let ends = self.segment_ends.as_deref().unwrap_or(&[]);
+        let mut start = 0;
+        ends.iter()
+            .map(|&end| {
+                let r = start..end;
+                start = end;
+                r
+            })
+            .collect()
+    }
+
+    // Executes a single instruction.
+    pub fn step(&mut self) -> Result<StepResult, VmError> {
+        if self.done {
+            return Ok(StepResult::Halted);
+        }
+
+        self.last_write = None;
+        self.last_break = None;
+        self.waiting = false;
+        // A jump past the end of memory lands on a 0, which isn't an opcode.
+        let code = self.read_mem(self.pc);
+        if Opcode::decode(code).is_none() {
+            match self.unknown_opcode_policy {
+                UnknownOpcodePolicy::Panic => panic!("Unexpected opcode: {}", code % 100),
+                UnknownOpcodePolicy::Error => {
+                    return Err(VmError::InvalidOpcode {
+                        pc: self.pc,
+                        value: code,
+                    })
+                }
+                UnknownOpcodePolicy::SkipAsNop => {
+                    self.pc += 1;
+                    self.steps += 1;
+                    return Ok(StepResult::Continued);
+                }
+            }
+        }
+
+        let inst = self.get_next_instruction()?;
+        let opcode = Opcode::from(inst.opcode);
+        if opcode == Opcode::Input && self.inputs.is_empty() {
+            if let Some(Ok(v)) = self.input_channel.as_ref().map(|rx| rx.recv()) {
+                self.inputs.push_back(v);
+            }
+        }
+        // An Input with nothing queued doesn't execute yet, it gets traced
+        // once it is resumed.
+        let waiting = opcode == Opcode::Input && self.inputs.is_empty();
+        if !waiting {
+            self.steps += 1;
+            self.opcode_counts[opcode.index()] += 1;
+        }
+        let in_range = self
+            .trace_filter
+            .as_ref()
+            .map_or(true, |r| r.contains(&self.pc));
+        if let (Some(trace), false, true) = (self.trace.as_mut(), waiting, in_range) {
+            trace.push(TraceEntry {
+                pc: self.pc,
+                opcode,
+            });
+        }
+        if let (Some(hook), false) = (self.trace_hook.as_mut(), waiting) {
+            hook(self.pc, &inst);
+        }
+        if let (Some(warnings), false) = (self.rb_warnings.as_mut(), waiting) {
+            for op in inst.operands.iter().filter(|op| op.mode == Mode::Relative) {
+                let address = op.value + self.relative_base;
+                if address < 0 {
+                    warnings.push(RelativeBaseWarning::NegativeAddress {
+                        pc: self.pc,
+                        address,
+                    });
+                }
+            }
+        }
+
+        match opcode {
+            Opcode::Halt => {
+                self.pc += 1;
+                self.done = true;
+                return Ok(StepResult::Halted);
+            }
+            Opcode::AdjustRelativeBase => {
+                let value = self.get_value(&inst.operands[0])?;
+                self.relative_base += value;
+                if let (Some(warnings), true) = (self.rb_warnings.as_mut(), self.relative_base < 0)
+                {
+                    warnings.push(RelativeBaseWarning::NegativeBase {
+                        pc: self.pc,
+                        base: self.relative_base,
+                    });
+                }
+                self.pc += 2;
+            }
+            Opcode::Add => {
+                let v1 = self.get_value(&inst.operands[0])?;
+                let v2 = self.get_value(&inst.operands[1])?;
+
+                // Parameters that an instruction writes to
+                // are always positional.
+                let dest = self.get_absolute_address(&inst.operands[2])?;
+                self.set_mem(dest, v1 + v2)?;
+                self.pc += 4;
+            }
+            Opcode::Multiply => {
+                let v1 = self.get_value(&inst.operands[0])?;
+                let v2 = self.get_value(&inst.operands[1])?;
+
+                // Parameters that an instruction writes to
+                // are always positional.
+                let dest = self.get_absolute_address(&inst.operands[2])?;
+                self.set_mem(dest, v1 * v2)?;
+                self.pc += 4;
+            }
+            Opcode::Input => {
+                // Pause without consuming the instruction so that the
+                // caller can supply more input and resume.
+                let inp = match self.inputs.pop_front() {
+                    Some(v) => v,
+                    None => {
+                        self.waiting = true;
+                        return Ok(StepResult::WaitingForInput);
+                    }
+                };
+                if self.checkpoint_inputs {
+                    self.input_checkpoint = Some(InputCheckpoint {
+                        pc: self.pc,
+                        relative_base: self.relative_base,
+                        outputs_len: self.outputs.len(),
+                        writes: self.writes,
+                        high_water_mark: self.high_water_mark,
+                        undo: vec![],
+                    });
+                }
+                if let Some(log) = self.io_log.as_mut() {
+                    log.push(IoEvent::Input(inp));
+                }
+                // In case of input, we only care about the address where to
+                // store the value.
+                let mut address = inst.operands[0].value;
+                if inst.operands[0].mode == Mode::Relative {
+                    address += self.relative_base;
+                }
+                let address = self.checked_address(address)?;
+                self.set_mem(address, inp)?;
+                self.pc += 2;
+            }
+            Opcode::Output => {
+                let value = self.get_value(&inst.operands[0])?;
+                self.output(value);
+                self.pc += 2;
+                return Ok(StepResult::Output(value));
+            }
+            Opcode::JumpIfTrue => {
+                if self.get_value(&inst.operands[0])? != 0 {
+                    let target = self.get_value(&inst.operands[1])?;
+                    self.pc = self.checked_address(target)?;
+                } else {
+                    self.pc += 3;
+                }
+            }
+            Opcode::JumpIfFalse => {
+                if self.get_value(&inst.operands[0])? == 0 {
+                    let target = self.get_value(&inst.operands[1])?;
+                    self.pc = self.checked_address(target)?;
+                } else {
+                    self.pc += 3;
+                }
+            }
+            Opcode::LessThan => {
+                let v1 = self.get_value(&inst.operands[0])?;
+                let v2 = self.get_value(&inst.operands[1])?;
+
+                let mut result = 0;
+                if v1 < v2 {
+                    result = 1
+                }
+                // Parameters that an instruction writes to
+                // are always positional.
+                let address = self.get_absolute_address(&inst.operands[2])?;
+                self.set_mem(address, result)?;
+                self.pc += 4;
+            }
+            Opcode::Equals => {
+                let v1 = self.get_value(&inst.operands[0])?;
+                let v2 = self.get_value(&inst.operands[1])?;
+
+                let mut result = 1;
+                if v1 != v2 {
+                    result = 0;
+                }
+                // Parameters that an instruction writes to
+                // are always positional.
+                let address = self.get_absolute_address(&inst.operands[2])?;
+                self.set_mem(address, result)?;
+                self.pc += 4;
+            }
+        }
+        Ok(StepResult::Continued)
+    }
+
+    fn get_absolute_address(&self, op: &Operand) -> Result<usize, VmError> {
+        match op.mode {
+            Mode::Position => self.checked_address(op.value),
+            Mode::Relative => self.checked_address(op.value + self.relative_base),
+            // Parameters that an instruction writes to are never immediate.
+            Mode::Immediate => Err(VmError::InvalidMode {
+                pc: self.pc,
+                value: self.read_mem(self.pc),
+            }),
+        }
+    }
+
+    fn checked_address(&self, address: i64) -> Result<usize, VmError> {
+        if address < 0 || address as usize >= self.max_memory {
+            return Err(VmError::BadAddress(address));
+        }
+        Ok(address as usize)
+    }
+
+    fn set_mem(&mut self, address: usize, v: i64) -> Result<(), VmError> {
+        if let Some(max) = self.max_writes {
+            if self.writes >= max {
+                return Err(VmError::WriteLimitExceeded);
+            }
+        }
+        self.writes += 1;
+        self.high_water_mark = self.high_water_mark.max(address);
+        self.last_write = Some(address);
+
+        self.ensure_mem_availability(address);
+        self.log_undo(address);
+        if let Some(deltas) = self.deltas.as_mut() {
+            deltas.push((address, self.bytecode[address], v));
+        }
+        self.bytecode[address] = v;
+        Ok(())
+    }
+
+    fn get_value(&mut self, op: &Operand) -> Result<i64, VmError> {
+        if op.mode == Mode::Immediate {
+            return Ok(op.value);
+        }
+
+        let address = self.get_absolute_address(op)?;
+        self.ensure_mem_availability(address);
+        Ok(self.bytecode[address])
+    }
+
+    // Grows memory so that address is a valid index.
+    fn ensure_mem_availability(&mut self, address: usize) {
+        if address >= self.bytecode.len() {
+            // double the memory
+            self.bytecode.resize((address + 1) * 2, 0);
+        }
+    }
+
+    fn get_next_instruction(&self) -> Result<Instruction, VmError> {
+        let decoded = decode_at(&self.bytecode, self.pc)?;
+        Ok(Instruction {
+            opcode: i64::from(decoded.opcode),
+            operands: decoded.operands,
+        })
+    }
+
+    // Memory cells from relative_base - radius to relative_base + radius,
+    // clamped at 0. Cells past the end of allocated memory read as 0.
+    pub fn relative_window(&self, radius: usize) -> Vec<(usize, i64)> {
+        let base = self.relative_base;
+        let end = base + radius as i64;
+        if end < 0 {
+            return vec![];
+        }
+        let start = (base - radius as i64).max(0) as usize;
+
+        (start..=end as usize)
+            .map(|a| (a, self.bytecode.get(a).cloned().unwrap_or(0)))
+            .collect()
+    }
+
+    // Every address that currently holds value.
+    pub fn find_value(&self, value: i64) -> Vec<usize> {
+        self.bytecode
+            .iter()
+            .enumerate()
+            .filter(|&(_, &v)| v == value)
+            .map(|(a, _)| a)
+            .collect()
+    }
+
+    // Highest address the program has written to so far. Unlike the length
+    // of memory, this isn't inflated by the doubling growth policy.
+    pub fn high_water_mark(&self) -> usize {
+        self.high_water_mark
+    }
+
+    pub fn bytecode(&self) -> Vec<i64> {
+        self.bytecode.clone()
+    }
+
+    // Reads a single memory cell. Cells past the end of allocated memory
+    // read as 0, the same as they would once memory grows to cover them.
+    pub fn read_mem(&self, addr: usize) -> i64 {
+        self.bytecode.get(addr).cloned().unwrap_or(0)
+    }
+
+    // Writes a single memory cell, growing memory if needed. Unlike writes
+    // made by the program this doesn't count against set_max_writes.
+    pub fn write_mem(&mut self, addr: usize, val: i64) {
+        self.ensure_mem_availability(addr);
+        self.log_undo(addr);
+        self.high_water_mark = self.high_water_mark.max(addr);
+        self.bytecode[addr] = val;
+    }
+
+    // Remembers the value at address for restore_input_checkpoint before
+    // it gets overwritten.
+    fn log_undo(&mut self, address: usize) {
+        if let Some(c) = self.input_checkpoint.as_mut() {
+            c.undo.push((address, self.bytecode[address]));
+        }
+    }
+
+    // Serializes the state for the web visualizer. Memory is cut after the
+    // high-water mark, dropping the zeros that only exist because of the
+    // doubling growth policy, but never before the last non-zero cell.
+    pub fn to_json(&self) -> String {
+        let last_used = self.bytecode.iter().rposition(|&v| v != 0).unwrap_or(0);
+        let end = (self.high_water_mark.max(last_used) + 1).min(self.bytecode.len());
+
+        format!(
+            "{{\"pc\":{},\"relative_base\":{},\"memory\":{},\"inputs\":{},\"outputs\":{},\"done\":{}}}",
+            self.pc,
+            self.relative_base,
+            json_array(&self.bytecode[..end]),
+            json_array(self.inputs.iter()),
+            json_array(&self.outputs),
+            self.done
+        )
+    }
+}
+
+fn json_array<'a>(values: impl IntoIterator<Item = &'a i64>) -> String {
+    let values: Vec<String> = values.into_iter().map(|v| v.to_string()).collect();
+    format!("[{}]", values.join(","))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mode() {
+        let m = 1002;
+        assert_eq!(Some(Mode::Position), Mode::try_parse(m, 0));
+        assert_eq!(Some(Mode::Immediate), Mode::try_parse(m, 1));
+        assert_eq!(Some(Mode::Position), Mode::try_parse(m, 2));
+
+        let m = 11101;
+        assert_eq!(Some(Mode::Immediate), Mode::try_parse(m, 0));
+        assert_eq!(Some(Mode::Immediate), Mode::try_parse(m, 1));
+        assert_eq!(Some(Mode::Immediate), Mode::try_parse(m, 2));
+
+        assert_eq!(None, Mode::try_parse(301, 0));
+    }
+
+    #[test]
+    fn test_add() {
+        let program = vec![1, 0, 0, 0, 99];
+        let expected = vec![2, 0, 0, 0, 99];
+
+        let mut vm = VM::new(program);
+        vm.run().unwrap();
+        assert_eq!(vm.bytecode, expected);
+        assert_eq!(5, vm.pc);
+    }
+
+    #[test]
+    fn test_multiply() {
+        let program = vec![2, 3, 0, 3, 99];
+        let expected = vec![2, 3, 0, 6, 99];
+
+        let mut vm = VM::new(program);
+        vm.run().unwrap();
+        assert_eq!(vm.bytecode, expected);
+        assert_eq!(5, vm.pc);
+    }
+
+    #[test]
+    fn test_simple_program() {
+        let program = vec![2, 4, 4, 5, 99, 0];
+        let expected = vec![2, 4, 4, 5, 99, 9801];
+
+        let mut vm = VM::new(program);
+        vm.run().unwrap();
+        assert_eq!(vm.bytecode, expected);
+        assert_eq!(5, vm.pc);
+    }
+
+    #[test]
+    fn test_simple_program2() {
+        let program = vec![1, 1, 1, 4, 99, 5, 6, 0, 99];
+        let expected = vec![30, 1, 1, 4, 2, 5, 6, 0, 99];
+
+        let mut vm = VM::new(program);
+        vm.run().unwrap();
+        assert_eq!(vm.bytecode, expected);
+        assert_eq!(9, vm.pc);
+    }
+
+    #[test]
+    fn test_with_inputs() {
+        let vm = VM::new(vec![99]).with_inputs(&[1, 2]).with_input(3);
+        assert_eq!(
+            vm.inputs.iter().cloned().collect::<Vec<i64>>(),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn test_input_output() {
+        let program = vec![1, 1, 1, 4, 99, 5, 6, 0, 3, 0, 4, 0, 99];
+        let expected = vec![99, 1, 1, 4, 2, 5, 6, 0, 3, 0, 4, 0, 99];
+        let mut vm = VM::new(program);
+        vm.set_inputs(&[99]);
+        vm.run().unwrap();
+        assert_eq!(vm.bytecode, expected);
+        assert_eq!(vm.outputs, vec![99]);
+    }
+
+    #[test]
+    fn test_jump_instructions() {
         // program that outputs 0 if the input was 0 otherwise 1.
         // It uses position mode for all arguments.
         let program = vec![3, 12, 6, 12, 15, 1, 13, 14, 13, 4, 13, 99, -1, 0, 1, 9];
         let mut vm = VM::new(program.clone());
         vm.set_inputs(&[0]);
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.outputs(), vec![0]);
 
         let mut vm = VM::new(program.clone());
         vm.set_inputs(&[9]);
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.outputs(), vec![1]);
 
         // Same program but uses immediate mode.
         let program = vec![3, 3, 1105, -1, 9, 1101, 0, 0, 12, 4, 12, 99, 1];
         let mut vm = VM::new(program.clone());
         vm.set_inputs(&[0]);
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.outputs(), vec![0]);
 
         let mut vm = VM::new(program.clone());
         vm.set_inputs(&[9]);
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.outputs(), vec![1]);
     }
 
@@ -497,13 +1474,13 @@ mod tests {
         let large_number = 1125899906842624i64;
         let program = vec![104, large_number, 99];
         let mut vm = VM::new(program);
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.get_last_output(), large_number);
 
         // This program should output a 16 digit number.
         let program = vec![1102, 34915192, 34915192, 7, 4, 7, 99, 0];
         let mut vm = VM::new(program);
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.get_last_output().to_string().len(), 16);
     }
 
@@ -517,10 +1494,672 @@ mod tests {
         ];
 
         let mut vm = VM::new(program);
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.outputs(), expected_outputs);
     }
 
+    #[test]
+    fn test_output_callback() {
+        use std::sync::{Arc, Mutex};
+
+        let seen = Arc::new(Mutex::new(vec![]));
+        let sink = seen.clone();
+
+        let program = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        let mut vm = VM::new(program);
+        vm.set_output_callback(move |v| sink.lock().unwrap().push(v));
+        vm.run().unwrap();
+        assert_eq!(*seen.lock().unwrap(), vm.outputs());
+    }
+
+    #[test]
+    fn test_run_ints_with() {
+        // Outputs 1 if the input is equal to 8 otherwise 0.
+        let program = vec![3, 9, 8, 9, 10, 9, 4, 9, 99, -1, 8];
+
+        let mut out = vec![];
+        let mut vm = VM::new(program.clone());
+        vm.run_ints_with("5\n".as_bytes(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "0\n");
+
+        let mut out = vec![];
+        let mut vm = VM::new(program.clone());
+        vm.run_ints_with("8\n".as_bytes(), &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "1\n");
+
+        // Running out of input just stops the VM.
+        let mut out = vec![];
+        let mut vm = VM::new(program);
+        vm.run_ints_with("".as_bytes(), &mut out).unwrap();
+        assert!(out.is_empty());
+        assert!(!vm.done);
+
+        // Jumps past the end of memory where there is no valid opcode.
+        let mut vm = VM::new(vec![1105, 1, 100]);
+        let err = vm.run_ints_with("".as_bytes(), &mut vec![]).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn test_find_value() {
+        let program = vec![1101, 42, 0, 7, 99, 42, 1, 0];
+        let mut vm = VM::new(program);
+        assert_eq!(vm.find_value(42), vec![1, 5]);
+
+        vm.run().unwrap();
+        assert_eq!(vm.find_value(42), vec![1, 5, 7]);
+        assert!(vm.find_value(1234).is_empty());
+    }
+
+    #[test]
+    fn test_unknown_opcode_policy() {
+        // 42 isn't an opcode, the rest outputs 7 and halts.
+        let program = vec![42, 104, 7, 99];
+
+        let mut vm = VM::new(program.clone());
+        vm.set_unknown_opcode_policy(UnknownOpcodePolicy::SkipAsNop);
+        vm.run().unwrap();
+        assert_eq!(vm.outputs(), vec![7]);
+
+        let mut vm = VM::new(program);
+        vm.set_unknown_opcode_policy(UnknownOpcodePolicy::Error);
+        assert_eq!(vm.run(), Err(VmError::InvalidOpcode { pc: 0, value: 42 }));
+    }
+
+    #[test]
+    fn test_invalid_instructions_are_errors_by_default() {
+        let mut vm = VM::new(vec![42, 99]);
+        assert_eq!(vm.run(), Err(VmError::InvalidOpcode { pc: 0, value: 42 }));
+
+        let mut vm = VM::new(vec![1105, 1, 3, 301, 5, 99]);
+        assert_eq!(vm.step(), Ok(StepResult::Continued),);
+        assert_eq!(vm.step(), Err(VmError::InvalidMode { pc: 3, value: 301 }));
+
+        // Writes to an immediate mode parameter.
+        let mut vm = VM::new(vec![11101, 1, 1, 5, 99, 0]);
+        assert_eq!(
+            vm.run(),
+            Err(VmError::InvalidMode {
+                pc: 0,
+                value: 11101
+            })
+        );
+
+        // Jumps past the end of memory.
+        let mut vm = VM::new(vec![1105, 1, 50]);
+        assert_eq!(vm.run(), Err(VmError::InvalidOpcode { pc: 50, value: 0 }));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unexpected opcode: 42")]
+    fn test_unknown_opcode_panic_policy() {
+        let mut vm = VM::new(vec![42, 99]);
+        vm.set_unknown_opcode_policy(UnknownOpcodePolicy::Panic);
+        let _ = vm.run();
+    }
+
+    #[test]
+    fn test_to_json() {
+        let mut vm = VM::new(vec![3, 9, 4, 9, 99]);
+        vm.set_inputs(&[5, 6]);
+        vm.run().unwrap();
+
+        let json = vm.to_json();
+        for key in ["pc", "relative_base", "memory", "inputs", "outputs", "done"].iter() {
+            assert!(json.contains(&format!("\"{}\":", key)), "missing {}", key);
+        }
+        assert_eq!(
+            json,
+            "{\"pc\":5,\"relative_base\":0,\"memory\":[3,9,4,9,99,0,0,0,0,5],\"inputs\":[6],\"outputs\":[5],\"done\":true}"
+        );
+    }
+
+    #[test]
+    fn test_reset_to_clears_written_cells() {
+        // Writes 11 far out, growing memory.
+        let mut vm = VM::new(vec![1101, 5, 6, 1000, 99]);
+        vm.run().unwrap();
+        let grown = vm.bytecode.len();
+        assert!(grown > 1000);
+        assert_eq!(vm.bytecode[1000], 11);
+
+        // Outputs the cells at 1000 and 3.
+        let program = [4, 1000, 4, 3, 99];
+        vm.reset_to(&program);
+        vm.run().unwrap();
+        assert_eq!(vm.outputs(), vec![0, 3]);
+        assert_eq!(vm.bytecode.len(), grown);
+        assert_eq!(&vm.bytecode[..5], &program);
+        assert!(vm.bytecode[5..].iter().all(|&v| v == 0));
+    }
+
+    #[test]
+    fn test_snapshot_restore() {
+        // Outputs 10 times each input, forever.
+        let mut vm = VM::new(vec![3, 11, 1002, 11, 10, 11, 4, 11, 1105, 1, 0, 0]);
+        vm.set_inputs(&[1]);
+        vm.run().unwrap();
+
+        let mut checkpoints = vec![vm.snapshot()];
+        vm.set_inputs(&[2]);
+        vm.run().unwrap();
+        checkpoints.push(vm.snapshot());
+        assert_eq!(vm.outputs(), vec![10, 20]);
+
+        vm.restore(checkpoints[0].clone());
+        assert_eq!(vm.snapshot(), checkpoints[0]);
+        vm.set_inputs(&[5]);
+        vm.run().unwrap();
+        assert_eq!(vm.outputs(), vec![10, 50]);
+
+        vm.restore(checkpoints[1].clone());
+        assert_eq!(vm.outputs(), vec![10, 20]);
+    }
+
+    #[test]
+    fn test_input_checkpoint() {
+        // Outputs 10 times the input.
+        let program = vec![3, 9, 1002, 9, 10, 9, 4, 9, 99, 0];
+        let mut vm = VM::new(program);
+        vm.checkpoint_at_input();
+        assert!(!vm.restore_input_checkpoint());
+
+        vm.set_inputs(&[1]);
+        vm.run().unwrap();
+        assert_eq!(vm.outputs(), vec![10]);
+
+        assert!(vm.restore_input_checkpoint());
+        assert_eq!(vm.pc, 0);
+        assert!(vm.outputs().is_empty());
+
+        vm.set_inputs(&[7]);
+        vm.run().unwrap();
+        assert_eq!(vm.outputs(), vec![70]);
+
+        // Writes made after the checkpoint are undone as well.
+        vm.write_mem(20, 5);
+        assert!(vm.restore_input_checkpoint());
+        assert_eq!(vm.read_mem(9), 0);
+        assert_eq!(vm.read_mem(20), 0);
+        vm.set_inputs(&[3]);
+        vm.run().unwrap();
+        assert_eq!(vm.outputs(), vec![30]);
+    }
+
+    #[test]
+    fn test_io_log() {
+        let mut vm = VM::new(vec![3, 0, 4, 0, 99]);
+        vm.set_echo_input(true);
+        vm.set_inputs(&[42]);
+        vm.run().unwrap();
+        assert_eq!(vm.io_log(), &[IoEvent::Input(42), IoEvent::Output(42)]);
+        assert_eq!(vm.outputs(), vec![42]);
+    }
+
+    #[test]
+    fn test_run_to_completion() {
+        let mut vm = VM::new(vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50]);
+        assert_eq!(
+            vm.run_to_completion(),
+            Ok(RunResult {
+                outputs: vec![],
+                memory: vec![3500, 9, 10, 70, 2, 3, 11, 0, 99, 30, 40, 50],
+                steps: 3,
+            })
+        );
+    }
+
+    #[test]
+    fn test_run_single_output() {
+        let mut vm = VM::new(vec![104, 7, 99]);
+        assert_eq!(vm.run_single_output(), Ok(7));
+
+        let mut vm = VM::new(vec![104, 7, 104, 8, 99]);
+        assert_eq!(
+            vm.run_single_output(),
+            Err(SingleOutputError::MultipleOutputs(vec![7, 8]))
+        );
+
+        let mut vm = VM::new(vec![99]);
+        assert_eq!(vm.run_single_output(), Err(SingleOutputError::NoOutput));
+    }
+
+    #[test]
+    fn test_diagnostic_result() {
+        assert_eq!(diagnostic_result(&[0, 0, 0, 15426686]), Ok(15426686));
+        assert_eq!(diagnostic_result(&[42]), Ok(42));
+        assert_eq!(
+            diagnostic_result(&[0, 3, 0, 7, 99]),
+            Err(vec![(1, 3), (3, 7)])
+        );
+        assert_eq!(diagnostic_result(&[]), Err(vec![]));
+    }
+
+    #[test]
+    fn test_try_last_output() {
+        let mut vm = VM::new(vec![3, 0, 4, 0, 99]);
+        assert_eq!(vm.try_last_output(), None);
+
+        vm.set_inputs(&[8]);
+        vm.run().unwrap();
+        assert_eq!(vm.try_last_output(), Some(8));
+        assert_eq!(vm.get_last_output(), 8);
+    }
+
+    #[test]
+    #[should_panic(expected = "VM hasn't produced any output yet")]
+    fn test_get_last_output_without_output() {
+        VM::new(vec![99]).get_last_output();
+    }
+
+    #[test]
+    fn test_write_just_past_the_end() {
+        // Writes 2 to address 4, the first cell past the program.
+        let mut vm = VM::new(vec![1101, 1, 1, 4]);
+        assert_eq!(vm.step(), Ok(StepResult::Continued));
+        assert_eq!(vm.read_mem(4), 2);
+
+        let mut vm = VM::new(vec![99]);
+        vm.write_mem(1, 5);
+        assert_eq!(vm.read_mem(1), 5);
+    }
+
+    #[test]
+    fn test_read_write_mem() {
+        // Adds addresses 5 and 6 into address 0.
+        let mut vm = VM::new(vec![1, 5, 6, 0, 99, 0, 0]);
+        vm.write_mem(5, 30);
+        vm.write_mem(6, 12);
+        vm.run().unwrap();
+        assert_eq!(vm.read_mem(0), 42);
+
+        assert_eq!(vm.read_mem(100), 0);
+        vm.write_mem(100, 7);
+        assert_eq!(vm.read_mem(100), 7);
+        assert_eq!(vm.high_water_mark(), 100);
+    }
+
+    #[test]
+    fn test_high_water_mark() {
+        let program = vec![1101, 1, 1, 50, 1101, 2, 2, 7, 99];
+        let mut vm = VM::new(program);
+        vm.run().unwrap();
+        assert_eq!(vm.high_water_mark(), 50);
+        assert!(vm.bytecode().len() > 50);
+    }
+
+    #[test]
+    fn test_breakpoints_survive_reset() {
+        let program = vec![1101, 1, 1, 9, 1101, 2, 2, 10, 99, 0, 0];
+        let mut vm = VM::new(program.clone());
+        vm.add_breakpoint(4);
+        assert_eq!(vm.resume(), Ok(RunState::Breakpoint(4)));
+        assert_eq!(vm.resume(), Ok(RunState::Halted));
+
+        vm.reset_to(&program);
+        assert_eq!(vm.resume(), Ok(RunState::Breakpoint(4)));
+
+        vm.reset_full(&program);
+        assert_eq!(vm.resume(), Ok(RunState::Halted));
+        assert_eq!(vm.bytecode()[9..], [2, 4]);
+    }
+
+    #[test]
+    fn test_breakpoint_at_start() {
+        let program = vec![1101, 1, 1, 5, 99, 0];
+        let mut vm = VM::new(program.clone());
+        vm.add_breakpoint(0);
+        assert_eq!(vm.resume(), Ok(RunState::Breakpoint(0)));
+        assert_eq!(vm.resume(), Ok(RunState::Halted));
+
+        vm.reset_to(&program);
+        assert_eq!(vm.resume(), Ok(RunState::Breakpoint(0)));
+        assert_eq!(vm.step_n(1), Ok(RunState::Paused));
+        vm.restore(VmState {
+            bytecode: program,
+            pc: 0,
+            relative_base: 0,
+            inputs: vec![],
+            outputs: vec![],
+            done: false,
+        });
+        assert_eq!(vm.resume(), Ok(RunState::Breakpoint(0)));
+    }
+
+    #[test]
+    fn test_step() {
+        let mut vm = VM::new(vec![3, 0, 4, 0, 99]);
+        assert_eq!(vm.step(), Ok(StepResult::WaitingForInput));
+        assert_eq!(vm.pc, 0);
+
+        assert!(vm.is_waiting());
+
+        vm.set_inputs(&[5]);
+        assert_eq!(vm.step(), Ok(StepResult::Continued));
+        assert!(!vm.is_waiting());
+        assert_eq!(vm.step(), Ok(StepResult::Output(5)));
+        assert_eq!(vm.step(), Ok(StepResult::Halted));
+        assert_eq!(vm.step(), Ok(StepResult::Halted));
+    }
+
+    #[test]
+    fn test_run_waits_for_input() {
+        let mut vm = VM::new(vec![3, 0, 4, 0, 3, 0, 4, 0, 99]);
+        vm.set_inputs(&[1]);
+        vm.run().unwrap();
+        assert!(vm.is_waiting());
+        assert_eq!(vm.outputs(), vec![1]);
+        assert_eq!(vm.pc, 4);
+
+        vm.set_inputs(&[2]);
+        vm.run().unwrap();
+        assert!(!vm.is_waiting());
+        assert!(vm.done);
+        assert_eq!(vm.outputs(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_channels() {
+        use std::sync::mpsc::channel;
+        use std::thread;
+
+        // Outputs every input doubled and halts once it reads a 0.
+        let doubler = vec![
+            3, 15, 1006, 15, 14, 102, 2, 15, 15, 4, 15, 1105, 1, 0, 99, 0,
+        ];
+
+        let (tx_a, rx_a) = channel();
+        let (tx_b, rx_b) = channel();
+        let (tx_c, rx_c) = channel();
+
+        let program = doubler.clone();
+        let a = thread::spawn(move || {
+            let mut vm = VM::new(program);
+            vm.set_input_channel(rx_a);
+            vm.set_output_channel(tx_b);
+            vm.run().unwrap();
+            vm.is_done()
+        });
+        let b = thread::spawn(move || {
+            let mut vm = VM::new(doubler);
+            vm.set_input_channel(rx_b);
+            vm.set_output_channel(tx_c);
+            vm.run().unwrap();
+            // The first VM halted and hung up without sending a 0.
+            vm.is_waiting()
+        });
+
+        for v in [1, 2, 3, 0].iter() {
+            tx_a.send(*v).unwrap();
+        }
+        assert!(a.join().unwrap());
+        assert!(b.join().unwrap());
+        assert_eq!(rx_c.iter().collect::<Vec<i64>>(), vec![4, 8, 12]);
+    }
+
+    #[test]
+    fn test_move_vm_into_thread() {
+        use std::sync::mpsc::channel;
+        use std::sync::{Arc, Mutex};
+        use std::thread;
+
+        // Day 7 amplifier: outputs 10 times the signal plus the phase.
+        let program = vec![
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+        let seen = Arc::new(Mutex::new(vec![]));
+        let sink = seen.clone();
+        let (tx, rx) = channel();
+
+        let mut vm = VM::new(program).with_input(4);
+        vm.set_input_channel(rx);
+        vm.set_output_callback(move |v| sink.lock().unwrap().push(v));
+        vm.set_trace(Box::new(|_, _| ()));
+        let amplifier = thread::spawn(move || {
+            vm.run().unwrap();
+            vm.get_last_output()
+        });
+
+        tx.send(3).unwrap();
+        assert_eq!(amplifier.join().unwrap(), 34);
+        assert_eq!(*seen.lock().unwrap(), vec![34]);
+    }
+
+    #[test]
+    fn test_drain_outputs() {
+        // Outputs 1, 2 forever.
+        let mut vm = VM::new(vec![104, 1, 104, 2, 1105, 1, 0]);
+        vm.step_n(2).unwrap();
+        assert_eq!(vm.outputs_slice(), &[1, 2]);
+        assert_eq!(vm.drain_outputs(), vec![1, 2]);
+        assert!(vm.outputs_slice().is_empty());
+
+        vm.step_n(3).unwrap();
+        assert_eq!(vm.drain_outputs(), vec![1, 2]);
+    }
+
+    #[test]
+    fn test_output_segments() {
+        // Outputs 1, then reads a value and outputs it along with 2.
+        let program = vec![104, 1, 3, 9, 4, 9, 104, 2, 99, 0];
+        let mut vm = VM::new(program);
+        vm.record_output_segments();
+
+        vm.run_till_output().unwrap();
+        vm.run_till_output().unwrap();
+        assert!(vm.is_waiting());
+        vm.set_inputs(&[5]);
+        vm.run_till_output().unwrap();
+
+        assert_eq!(vm.output_segments(), vec![0..1, 1..1, 1..2]);
+        vm.run().unwrap();
+        assert_eq!(vm.outputs(), vec![1, 5, 2]);
+        assert_eq!(vm.output_segments().len(), 3);
+    }
+
+    #[test]
+    fn test_opcode_counts() {
+        // Counts 3 down to 0 in the loop at 4..11, then outputs and halts.
+        let program = vec![1101, 3, 0, 14, 1001, 14, -1, 14, 1005, 14, 4, 4, 14, 99, 0];
+        let mut vm = VM::new(program);
+        vm.run().unwrap();
+        assert_eq!(vm.instruction_count(), 9);
+        assert_eq!(
+            vm.opcode_counts(),
+            vec![
+                (Opcode::Add, 4),
+                (Opcode::Output, 1),
+                (Opcode::JumpIfTrue, 3),
+                (Opcode::Halt, 1),
+            ]
+        );
+
+        vm.reset(vec![99]);
+        assert!(vm.opcode_counts().is_empty());
+    }
+
+    #[test]
+    fn test_status_line() {
+        // Outputs 1, 2 forever.
+        let mut vm = VM::new(vec![104, 1, 104, 2, 1105, 1, 0]);
+        assert_eq!(vm.step_n(7), Ok(RunState::Paused));
+        assert_eq!(vm.instruction_count(), 7);
+        assert_eq!(vm.status_line(), "pc=2 rb=0 steps=7 out=5");
+
+        vm.step_n(1_500_000).unwrap();
+        assert_eq!(vm.status_line(), "pc=2 rb=0 steps=1.5M out=1000005");
+    }
+
+    #[test]
+    fn test_step_n() {
+        let program = vec![1101, 1, 1, 9, 1101, 2, 2, 10, 99, 0, 0];
+        let mut vm = VM::new(program.clone());
+        assert_eq!(vm.step_n(2), Ok(RunState::Paused));
+        assert_eq!(vm.pc, 8);
+
+        let mut vm = VM::new(program);
+        assert_eq!(vm.step_n(10), Ok(RunState::Halted));
+        assert_eq!(vm.pc, 9);
+        assert_eq!(vm.bytecode()[9..], [2, 4]);
+
+        let mut vm = VM::new(vec![3, 0, 99]);
+        assert_eq!(vm.step_n(10), Ok(RunState::WaitingForInput));
+        assert_eq!(vm.pc, 0);
+    }
+
+    #[test]
+    fn test_run_timeout() {
+        // Jumps back to itself forever.
+        let mut vm = VM::new(vec![1105, 1, 0]);
+        assert_eq!(
+            vm.run_timeout(Duration::from_millis(20)),
+            Ok(RunState::Timeout)
+        );
+
+        let mut vm = VM::new(vec![104, 1, 99]);
+        assert_eq!(vm.run_timeout(Duration::from_secs(1)), Ok(RunState::Halted));
+
+        let mut vm = VM::new(vec![42]);
+        assert_eq!(
+            vm.run_timeout(Duration::from_secs(1)),
+            Err(VmError::InvalidOpcode { pc: 0, value: 42 })
+        );
+    }
+
+    #[test]
+    fn test_detect_output_period() {
+        // Outputs 1, 2 forever.
+        let mut vm = VM::new(vec![104, 1, 104, 2, 1105, 1, 0]);
+        assert_eq!(vm.detect_output_period(20, 1000), Some(2));
+
+        // Outputs 1, 1, 2 forever; a repeated value isn't the start of a cycle.
+        let mut vm = VM::new(vec![104, 1, 104, 1, 104, 2, 1105, 1, 0]);
+        assert_eq!(vm.detect_output_period(30, 1000), Some(3));
+
+        // Counts up from 0 so it never repeats.
+        let mut vm = VM::new(vec![4, 9, 101, 1, 9, 9, 1105, 1, 0, 0]);
+        assert_eq!(vm.detect_output_period(20, 1000), None);
+
+        // Halts after a single output, not enough to tell.
+        let mut vm = VM::new(vec![104, 1, 99]);
+        assert_eq!(vm.detect_output_period(20, 1000), None);
+
+        // Loops forever without any output, stopped by the step budget.
+        let mut vm = VM::new(vec![1105, 1, 0]);
+        assert_eq!(vm.detect_output_period(20, 1000), None);
+        assert_eq!(vm.pc, 0);
+    }
+
+    #[test]
+    fn test_step_with_delta() {
+        let mut vm = VM::new(vec![1101, 3, 5, 5, 99, 7]);
+        assert_eq!(
+            vm.step_with_delta(),
+            Ok((Some(Opcode::Add), vec![(5, 7, 8)]))
+        );
+        assert_eq!(vm.step_with_delta(), Ok((Some(Opcode::Halt), vec![])));
+
+        let mut vm = VM::new(vec![42, 99]);
+        assert_eq!(
+            vm.step_with_delta(),
+            Err(VmError::InvalidOpcode { pc: 0, value: 42 })
+        );
+        vm.set_unknown_opcode_policy(UnknownOpcodePolicy::SkipAsNop);
+        assert_eq!(vm.step_with_delta(), Ok((None, vec![])));
+        assert_eq!(vm.step_with_delta(), Ok((Some(Opcode::Halt), vec![])));
+    }
+
+    #[test]
+    fn test_watchpoints() {
+        let program = vec![1101, 1, 1, 9, 1101, 2, 2, 10, 99, 0, 0];
+        let mut vm = VM::new(program);
+        vm.add_watchpoint(10);
+        assert_eq!(vm.resume(), Ok(RunState::Watchpoint(10)));
+        assert_eq!(vm.pc, 8);
+        assert_eq!(vm.resume(), Ok(RunState::Halted));
+    }
+
+    #[test]
+    fn test_diagnostic_code() {
+        let mut vm = VM::new(vec![104, 0, 104, 0, 104, 1234, 99]);
+        assert_eq!(vm.diagnostic_code(), Err(DiagnosticError::NoOutput));
+        vm.run().unwrap();
+        assert_eq!(vm.diagnostic_code(), Ok(1234));
+
+        let mut vm = VM::new(vec![104, 0, 104, 7, 104, 0, 104, 1234, 99]);
+        vm.run().unwrap();
+        assert_eq!(
+            vm.diagnostic_code(),
+            Err(DiagnosticError::FailedTests(vec![(1, 7)]))
+        );
+    }
+
+    #[test]
+    fn test_relative_base_warnings() {
+        // Moves the relative base to -3 and reads ~5 (address 2) and ~1.
+        let program = vec![109, -3, 204, 5, 204, 1, 99];
+        let mut vm = VM::new(program.clone());
+        vm.enable_relative_base_checks();
+        assert_eq!(vm.run(), Err(VmError::BadAddress(-2)));
+        assert_eq!(vm.outputs(), vec![204]);
+        assert_eq!(
+            vm.relative_base_warnings(),
+            &[
+                RelativeBaseWarning::NegativeBase { pc: 0, base: -3 },
+                RelativeBaseWarning::NegativeAddress { pc: 4, address: -2 },
+            ]
+        );
+
+        // Off by default.
+        let mut vm = VM::new(program);
+        let _ = vm.run();
+        assert!(vm.relative_base_warnings().is_empty());
+    }
+
+    #[test]
+    fn test_bad_address() {
+        // Reads from address -1.
+        let mut vm = VM::new(vec![4, -1, 99]);
+        assert_eq!(vm.run(), Err(VmError::BadAddress(-1)));
+
+        // Writes through a negative relative base.
+        let mut vm = VM::new(vec![109, -5, 21101, 1, 1, 0, 99]);
+        assert_eq!(vm.run(), Err(VmError::BadAddress(-5)));
+
+        // Jumps to a negative address.
+        let mut vm = VM::new(vec![1105, 1, -3]);
+        assert_eq!(vm.run(), Err(VmError::BadAddress(-3)));
+
+        let program = vec![1101, 1, 1, 1000, 99];
+        let mut vm = VM::new(program.clone());
+        vm.set_max_memory(1000);
+        assert_eq!(vm.run(), Err(VmError::BadAddress(1000)));
+
+        // Far beyond the default limit.
+        let mut vm = VM::new(vec![1101, 1, 1, 1_000_000_000_000, 99]);
+        assert_eq!(vm.run(), Err(VmError::BadAddress(1_000_000_000_000)));
+
+        let mut vm = VM::new(program);
+        vm.set_max_memory(1001);
+        assert_eq!(vm.run(), Ok(()));
+        assert_eq!(vm.read_mem(1000), 2);
+    }
+
+    #[test]
+    fn test_max_writes() {
+        let program = vec![
+            1101, 1, 1, 7, // store 1 + 1 at 7
+            1105, 1, 0, // jump back to 0
+            0,
+        ];
+        let mut vm = VM::new(program);
+        vm.set_max_writes(10);
+        assert_eq!(vm.run(), Err(VmError::WriteLimitExceeded));
+        assert_eq!(vm.writes, 10);
+    }
+
     #[test]
     fn test_relative_mode_input() {
         let program = vec![
@@ -533,8 +2172,26 @@ mod tests {
         ]; // halt
         let mut vm = VM::new(program);
         vm.set_inputs(&[111, 55]);
-        vm.run();
+        vm.run().unwrap();
         assert_eq!(vm.bytecode()[155], 111);
         assert_eq!(vm.bytecode()[50], 55);
     }
+
+    #[test]
+    fn test_relative_window() {
+        let program = vec![109, 100, 109, 25, 109, -20, 203, 50, 103, 50, 99];
+        let mut vm = VM::new(program);
+        vm.set_inputs(&[111, 55]);
+        vm.run().unwrap();
+
+        let window = vm.relative_window(2);
+        let addresses: Vec<usize> = window.iter().map(|&(a, _)| a).collect();
+        assert_eq!(addresses, vec![103, 104, 105, 106, 107]);
+        assert!(window.iter().all(|&(_, v)| v == 0));
+
+        let mut vm = VM::new(vec![109, 3, 99, 7, 8, 9]);
+        vm.run().unwrap();
+        assert_eq!(vm.relative_window(1), vec![(2, 99), (3, 7), (4, 8)]);
+        assert_eq!(vm.relative_window(5)[0], (0, 109));
+    }
 }
@@ -0,0 +1,288 @@
+// Helpers for chaining amplifier programs (day 7).
+
+use std::collections::HashSet;
+use std::ops::Range;
+
+use itertools::Itertools;
+
+use super::{VmError, VM};
+
+#[derive(Debug, PartialEq)]
+pub enum PhaseError {
+    Duplicate(i64),
+    OutOfRange(i64),
+    WrongLength { expected: usize, found: usize },
+    // The phases were valid but an amplifier failed.
+    Vm(VmError),
+}
+
+impl From<VmError> for PhaseError {
+    fn from(e: VmError) -> Self {
+        PhaseError::Vm(e)
+    }
+}
+
+// Checks that phases is a permutation of range.
+pub fn validate_phases(phases: &[i64], range: Range<i64>) -> Result<(), PhaseError> {
+    let mut seen = HashSet::new();
+    for &p in phases {
+        if !range.contains(&p) {
+            return Err(PhaseError::OutOfRange(p));
+        }
+        if !seen.insert(p) {
+            return Err(PhaseError::Duplicate(p));
+        }
+    }
+
+    let expected = (range.end - range.start) as usize;
+    if phases.len() != expected {
+        return Err(PhaseError::WrongLength {
+            expected,
+            found: phases.len(),
+        });
+    }
+
+    Ok(())
+}
+
+fn amplifiers(program: &[i64], phases: &[i64]) -> Vec<VM> {
+    phases
+        .iter()
+        .map(|&p| VM::new(program.to_vec()).with_input(p))
+        .collect()
+}
+
+// Part 1: runs each amplifier until its first output, feeding it to the next
+// one. Returns the output of the last amplifier.
+pub fn chain_output(program: &[i64], phases: &[i64]) -> Result<i64, VmError> {
+    run_amplifier_chain(program, phases, false)
+}
+
+// Part 2: passes signals around the loop one output at a time until the last
+// amplifier halts. Returns the last output of the last amplifier.
+pub fn feedback_output(program: &[i64], phases: &[i64]) -> Result<i64, VmError> {
+    run_amplifier_chain(program, phases, true)
+}
+
+// Same as chain_output but first checks that phases is a permutation of range.
+pub fn try_chain_output(
+    program: &[i64],
+    phases: &[i64],
+    range: Range<i64>,
+) -> Result<i64, PhaseError> {
+    validate_phases(phases, range)?;
+    Ok(chain_output(program, phases)?)
+}
+
+// Same as feedback_output but first checks that phases is a permutation of
+// range.
+pub fn try_feedback_output(
+    program: &[i64],
+    phases: &[i64],
+    range: Range<i64>,
+) -> Result<i64, PhaseError> {
+    validate_phases(phases, range)?;
+    Ok(feedback_output(program, phases)?)
+}
+
+// Runs as many amplifiers as there are phases. Each one is run until its next
+// output, which becomes the input of the next amplifier. Without feedback
+// that's a single pass, with feedback the last output goes back to the first
+// amplifier until the last one halts. Returns the last output of the last
+// amplifier, or the first error any amplifier runs into.
+pub fn run_amplifier_chain(
+    program: &[i64],
+    phases: &[i64],
+    feedback: bool,
+) -> Result<i64, VmError> {
+    let vms = run_chain(program, phases, feedback)?;
+    Ok(vms.last().map_or(0, |vm| vm.get_last_output()))
+}
+
+// Number of instructions each amplifier executed in a feedback run.
+pub fn amplifier_step_counts(program: &[i64], phases: &[i64]) -> Result<Vec<u64>, VmError> {
+    Ok(run_chain(program, phases, true)?
+        .iter()
+        .map(|vm| vm.instruction_count())
+        .collect())
+}
+
+// Tries every permutation of range and returns the highest output along with
+// the lexicographically smallest permutation that produced it, so that ties
+// are always broken the same way.
+pub fn best_phase_canonical(
+    program: &[i64],
+    range: Range<i64>,
+    feedback: bool,
+) -> Result<(i64, Vec<i64>), VmError> {
+    let n = (range.end - range.start) as usize;
+    let mut best: Option<(i64, Vec<i64>)> = None;
+    for phases in range.permutations(n) {
+        let output = run_amplifier_chain(program, &phases, feedback)?;
+
+        let better = match &best {
+            None => true,
+            Some((max, winner)) => output > *max || (output == *max && phases < *winner),
+        };
+        if better {
+            best = Some((output, phases));
+        }
+    }
+
+    Ok(best.unwrap_or((0, vec![])))
+}
+
+fn run_chain(program: &[i64], phases: &[i64], feedback: bool) -> Result<Vec<VM>, VmError> {
+    let mut vms = amplifiers(program, phases);
+    let mut signal = 0;
+
+    while !vms.last().map_or(true, |vm| vm.done) {
+        let mut progressed = false;
+        for vm in vms.iter_mut() {
+            let before = vm.outputs.len();
+            vm.set_inputs(&[signal]);
+            vm.run_till_output()?;
+            if vm.outputs.len() > before {
+                signal = vm.get_last_output();
+                progressed = true;
+            }
+        }
+
+        // Every amplifier is stuck, more rounds won't change anything.
+        if !feedback || !progressed {
+            break;
+        }
+    }
+
+    Ok(vms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_feedback_output() {
+        let program = [
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        assert_eq!(feedback_output(&program, &[9, 8, 7, 6, 5]), Ok(139629729));
+    }
+
+    #[test]
+    fn test_run_amplifier_chain() {
+        let program = [
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+        assert_eq!(
+            run_amplifier_chain(&program, &[4, 3, 2, 1, 0], false),
+            Ok(43210)
+        );
+        // Chains of other lengths just have fewer or more digits.
+        assert_eq!(run_amplifier_chain(&program, &[2, 1, 0], false), Ok(210));
+        assert_eq!(
+            run_amplifier_chain(&program, &[6, 5, 4, 3, 2, 1, 0], false),
+            Ok(6543210)
+        );
+
+        let program = [
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        assert_eq!(
+            run_amplifier_chain(&program, &[9, 8, 7, 6, 5], true),
+            Ok(139629729)
+        );
+
+        assert_eq!(
+            run_amplifier_chain(&[42], &[0, 1], false),
+            Err(VmError::InvalidOpcode { pc: 0, value: 42 })
+        );
+    }
+
+    #[test]
+    fn test_amplifier_step_counts() {
+        let program = [
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        let counts = amplifier_step_counts(&program, &[9, 8, 7, 6, 5]).unwrap();
+        assert_eq!(counts.len(), 5);
+        assert!(counts.iter().all(|&c| c > 0));
+    }
+
+    #[test]
+    fn test_best_phase_canonical() {
+        let program = [
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+        assert_eq!(
+            best_phase_canonical(&program, 0..5, false),
+            Ok((43210, vec![4, 3, 2, 1, 0]))
+        );
+
+        // Outputs phase + signal, so every permutation ties on the sum.
+        let program = [3, 11, 3, 12, 1, 11, 12, 13, 4, 13, 99, 0, 0, 0];
+        assert_eq!(
+            best_phase_canonical(&program, 0..5, false),
+            Ok((10, vec![0, 1, 2, 3, 4]))
+        );
+        assert_eq!(
+            best_phase_canonical(&program, 5..10, true),
+            Ok((35, vec![5, 6, 7, 8, 9]))
+        );
+    }
+
+    #[test]
+    fn test_validate_phases() {
+        assert_eq!(validate_phases(&[4, 3, 2, 1, 0], 0..5), Ok(()));
+        assert_eq!(
+            validate_phases(&[0, 0, 1, 2, 3], 0..5),
+            Err(PhaseError::Duplicate(0))
+        );
+        assert_eq!(
+            validate_phases(&[9, 8, 7, 6, 5], 0..5),
+            Err(PhaseError::OutOfRange(9))
+        );
+        assert_eq!(
+            validate_phases(&[0, 1, 2], 0..5),
+            Err(PhaseError::WrongLength {
+                expected: 5,
+                found: 3
+            })
+        );
+    }
+
+    #[test]
+    fn test_try_chain_output() {
+        let program = [
+            3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+        ];
+        assert_eq!(
+            try_chain_output(&program, &[4, 3, 2, 1, 0], 0..5),
+            Ok(43210)
+        );
+        assert_eq!(
+            try_chain_output(&program, &[0, 0, 1, 2, 3], 0..5),
+            Err(PhaseError::Duplicate(0))
+        );
+        assert_eq!(
+            try_chain_output(&[42], &[0, 1], 0..2),
+            Err(PhaseError::Vm(VmError::InvalidOpcode { pc: 0, value: 42 }))
+        );
+
+        let program = [
+            3, 26, 1001, 26, -4, 26, 3, 27, 1002, 27, 2, 27, 1, 27, 26, 27, 4, 27, 1001, 28, -1,
+            28, 1005, 28, 6, 99, 0, 0, 5,
+        ];
+        assert_eq!(
+            try_feedback_output(&program, &[9, 8, 7, 6, 5], 5..10),
+            Ok(139629729)
+        );
+        assert_eq!(
+            try_feedback_output(&program, &[9, 8, 7, 6, 4], 5..10),
+            Err(PhaseError::OutOfRange(4))
+        );
+    }
+}
@@ -0,0 +1,206 @@
+// Helpers for Intcode programs that talk in ASCII.
+
+use std::io::{self, BufRead, Write};
+
+use super::{VmError, VM};
+
+// Splits ASCII outputs into lines at newline (10). Values outside the ASCII
+// range are skipped.
+pub fn ascii_lines(outputs: &[i64]) -> Vec<String> {
+    let mut lines = vec![];
+    let mut current = String::new();
+
+    for &v in outputs {
+        if v == 10 {
+            lines.push(current);
+            current = String::new();
+        } else if (0..128).contains(&v) {
+            current.push(v as u8 as char);
+        }
+    }
+
+    if !current.is_empty() {
+        lines.push(current);
+    }
+
+    lines
+}
+
+// Like the Unix strings tool: every run of at least min_len printable ASCII
+// values in the program, as (start address, text).
+pub fn find_ascii_strings(program: &[i64], min_len: usize) -> Vec<(usize, String)> {
+    let mut result = vec![];
+    let mut start = 0;
+    let mut current = String::new();
+
+    // A trailing non-printable sentinel flushes the last run.
+    for (i, &v) in program.iter().chain(std::iter::once(&0)).enumerate() {
+        if (32..127).contains(&v) {
+            if current.is_empty() {
+                start = i;
+            }
+            current.push(v as u8 as char);
+        } else {
+            if current.len() >= min_len {
+                result.push((start, current.clone()));
+            }
+            current.clear();
+        }
+    }
+
+    result
+}
+
+impl VM {
+    // Queues a single character as input.
+    pub fn feed_char(&mut self, c: char) -> Result<(), VmError> {
+        if !c.is_ascii() {
+            return Err(VmError::NonAsciiInput(c));
+        }
+        self.inputs.push_back(c as i64);
+        Ok(())
+    }
+
+    // Queues every byte of s followed by a newline.
+    pub fn push_ascii_line(&mut self, s: &str) {
+        self.inputs.extend(s.bytes().map(i64::from));
+        self.inputs.push_back(10);
+    }
+
+    // Removes the ASCII values from the outputs and returns them as text.
+    // Values of 128 and above, e.g. a final score, stay in the outputs.
+    pub fn drain_ascii_output(&mut self) -> String {
+        let (ascii, rest): (Vec<i64>, Vec<i64>) =
+            self.outputs.iter().partition(|&&v| (0..128).contains(&v));
+        self.outputs = rest;
+        ascii.iter().map(|&v| v as u8 as char).collect()
+    }
+
+    // Runs an ASCII program on stdin/stdout one keypress at a time.
+    pub fn run_stdio_chars(&mut self) -> io::Result<()> {
+        let stdin = io::stdin();
+        self.run_chars_with(stdin.lock(), io::stdout())
+    }
+
+    // Character mode counterpart of run_ints_with: reads a single byte
+    // whenever the program needs input and writes ASCII outputs as
+    // characters. Outputs outside the ASCII range are written as numbers on
+    // their own line. A VmError is returned as InvalidData.
+    pub fn run_chars_with<R: BufRead, W: Write>(
+        &mut self,
+        input: R,
+        mut output: W,
+    ) -> io::Result<()> {
+        let mut bytes = input.bytes();
+        while !self.done {
+            if self.needs_input() {
+                let b = match bytes.next() {
+                    Some(b) => b?,
+                    None => return Ok(()),
+                };
+                self.feed_char(b as char)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            }
+
+            let before = self.outputs.len();
+            self.run_till_output()
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            for &v in &self.outputs[before..] {
+                if (0..128).contains(&v) {
+                    write!(output, "{}", v as u8 as char)?;
+                } else {
+                    writeln!(output, "{}", v)?;
+                }
+            }
+            output.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ascii_lines() {
+        assert_eq!(ascii_lines(&[65, 10, 66, 67, 10]), vec!["A", "BC"]);
+        assert_eq!(ascii_lines(&[65, 10, 10, 66]), vec!["A", "", "B"]);
+        assert!(ascii_lines(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_find_ascii_strings() {
+        // Prints the string at address 14 up to the terminating 0. The
+        // halt at 12 is a lone 'c' which is too short to count.
+        let program = [
+            109, 14, 1206, 0, 12, 204, 0, 109, 1, 1105, 1, 2, 99, 0, 72, 69, 76, 76, 79, 0,
+        ];
+        assert_eq!(
+            find_ascii_strings(&program, 4),
+            vec![(14, "HELLO".to_owned())]
+        );
+
+        let mut vm = VM::new(program.to_vec());
+        vm.run().unwrap();
+        assert_eq!(ascii_lines(&vm.outputs()), vec!["HELLO"]);
+        assert_eq!(
+            find_ascii_strings(&[72, 73, 0, 72], 2),
+            vec![(0, "HI".to_owned())]
+        );
+        assert_eq!(
+            find_ascii_strings(&[0, 72, 73], 2),
+            vec![(1, "HI".to_owned())]
+        );
+    }
+
+    #[test]
+    fn test_feed_char() {
+        let mut vm = VM::new(vec![]);
+        vm.feed_char('h').unwrap();
+        vm.feed_char('i').unwrap();
+        vm.feed_char('\n').unwrap();
+        assert_eq!(
+            vm.inputs.iter().cloned().collect::<Vec<i64>>(),
+            vec![104, 105, 10]
+        );
+
+        assert_eq!(vm.feed_char('é'), Err(VmError::NonAsciiInput('é')));
+        assert_eq!(vm.inputs.len(), 3);
+    }
+
+    #[test]
+    fn test_ascii_line_io() {
+        // Echoes three characters and then outputs 1000.
+        let program = vec![3, 20, 4, 20, 3, 20, 4, 20, 3, 20, 4, 20, 104, 1000, 99];
+        let mut vm = VM::new(program);
+        vm.push_ascii_line("ok");
+        assert_eq!(
+            vm.inputs.iter().cloned().collect::<Vec<i64>>(),
+            vec![111, 107, 10]
+        );
+
+        vm.run().unwrap();
+        assert_eq!(vm.drain_ascii_output(), "ok\n");
+        assert_eq!(vm.outputs(), vec![1000]);
+        assert_eq!(vm.drain_ascii_output(), "");
+    }
+
+    #[test]
+    fn test_run_chars_with() {
+        // Echoes two characters back, then outputs 1000.
+        let program = vec![3, 20, 4, 20, 3, 20, 4, 20, 104, 1000, 99];
+        let mut vm = VM::new(program);
+        let mut out = vec![];
+        vm.run_chars_with(&b"ok"[..], &mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "ok1000\n");
+
+        // 42 isn't an opcode.
+        let mut vm = VM::new(vec![104, 65, 42]);
+        let mut out = vec![];
+        let err = vm.run_chars_with(&b""[..], &mut out).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert_eq!(out, b"A");
+    }
+}
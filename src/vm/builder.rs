@@ -0,0 +1,144 @@
+// Programmatic construction of Intcode programs for test fixtures.
+
+use super::{Mode, Opcode, Operand};
+
+impl Operand {
+    pub fn pos(address: i64) -> Operand {
+        Operand::new(address, Mode::Position)
+    }
+
+    pub fn imm(value: i64) -> Operand {
+        Operand::new(value, Mode::Immediate)
+    }
+
+    pub fn rel(offset: i64) -> Operand {
+        Operand::new(offset, Mode::Relative)
+    }
+}
+
+// Appends instructions with their parameter modes encoded into the opcode.
+#[derive(Debug, Default)]
+pub struct ProgramBuilder {
+    program: Vec<i64>,
+}
+
+impl ProgramBuilder {
+    pub fn new() -> ProgramBuilder {
+        ProgramBuilder { program: vec![] }
+    }
+
+    // Address of the next instruction, handy for jump targets.
+    pub fn len(&self) -> usize {
+        self.program.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.program.is_empty()
+    }
+
+    fn push(mut self, opcode: Opcode, operands: &[Operand]) -> Self {
+        let mut code = i64::from(opcode);
+        let mut digit = 100;
+        for op in operands {
+            let mode = match op.mode {
+                Mode::Position => 0,
+                Mode::Immediate => 1,
+                Mode::Relative => 2,
+            };
+            code += mode * digit;
+            digit *= 10;
+        }
+
+        self.program.push(code);
+        self.program.extend(operands.iter().map(|op| op.value));
+        self
+    }
+
+    pub fn add(self, a: Operand, b: Operand, dest: Operand) -> Self {
+        self.push(Opcode::Add, &[a, b, dest])
+    }
+
+    pub fn multiply(self, a: Operand, b: Operand, dest: Operand) -> Self {
+        self.push(Opcode::Multiply, &[a, b, dest])
+    }
+
+    pub fn input(self, dest: Operand) -> Self {
+        self.push(Opcode::Input, &[dest])
+    }
+
+    pub fn output(self, src: Operand) -> Self {
+        self.push(Opcode::Output, &[src])
+    }
+
+    pub fn jump_if_true(self, cond: Operand, target: Operand) -> Self {
+        self.push(Opcode::JumpIfTrue, &[cond, target])
+    }
+
+    pub fn jump_if_false(self, cond: Operand, target: Operand) -> Self {
+        self.push(Opcode::JumpIfFalse, &[cond, target])
+    }
+
+    pub fn less_than(self, a: Operand, b: Operand, dest: Operand) -> Self {
+        self.push(Opcode::LessThan, &[a, b, dest])
+    }
+
+    pub fn equals(self, a: Operand, b: Operand, dest: Operand) -> Self {
+        self.push(Opcode::Equals, &[a, b, dest])
+    }
+
+    pub fn adjust_relative_base(self, offset: Operand) -> Self {
+        self.push(Opcode::AdjustRelativeBase, &[offset])
+    }
+
+    pub fn halt(self) -> Self {
+        self.push(Opcode::Halt, &[])
+    }
+
+    // Raw cells, e.g. for a data section after the code.
+    pub fn data(mut self, values: &[i64]) -> Self {
+        self.program.extend_from_slice(values);
+        self
+    }
+
+    pub fn build(self) -> Vec<i64> {
+        self.program
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::VM;
+
+    #[test]
+    fn test_echo() {
+        let program = ProgramBuilder::new()
+            .input(Operand::pos(0))
+            .output(Operand::pos(0))
+            .halt()
+            .build();
+        assert_eq!(program, vec![3, 0, 4, 0, 99]);
+
+        let mut vm = VM::new(program);
+        vm.set_inputs(&[42]);
+        vm.run().unwrap();
+        assert_eq!(vm.outputs(), vec![42]);
+    }
+
+    #[test]
+    fn test_modes_and_jumps() {
+        // Outputs 3, 2, 1 by counting down the cell at 10.
+        let program = ProgramBuilder::new()
+            .output(Operand::pos(10))
+            .add(Operand::pos(10), Operand::imm(-1), Operand::pos(10))
+            .jump_if_true(Operand::pos(10), Operand::imm(0))
+            .halt()
+            .data(&[3])
+            .build();
+        assert_eq!(program, vec![4, 10, 1001, 10, -1, 10, 1005, 10, 0, 99, 3]);
+
+        let mut vm = VM::new(program);
+        vm.run().unwrap();
+        assert_eq!(vm.outputs(), vec![3, 2, 1]);
+    }
+}
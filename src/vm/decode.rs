@@ -0,0 +1,319 @@
+// Static decoding of Intcode programs without running them.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use super::{Mode, Opcode, Operand, VmError, VM};
+
+#[derive(Debug)]
+pub struct DecodedInstruction {
+    pub address: usize,
+    pub opcode: Opcode,
+    pub operands: Vec<Operand>,
+}
+
+// Decodes the instruction at an arbitrary address of the program.
+pub fn decode_at(program: &[i64], address: usize) -> Result<DecodedInstruction, VmError> {
+    let code = *program
+        .get(address)
+        .ok_or(VmError::MissingOperands { pc: address })?;
+    let opcode = Opcode::decode(code).ok_or(VmError::InvalidOpcode {
+        pc: address,
+        value: code,
+    })?;
+
+    let mut operands = vec![];
+    for i in 0..opcode.operand_count() {
+        let value = *program
+            .get(address + 1 + i)
+            .ok_or(VmError::MissingOperands { pc: address })?;
+        let mode = Mode::try_parse(code, i).ok_or(VmError::InvalidMode {
+            pc: address,
+            value: code,
+        })?;
+        operands.push(Operand::new(value, mode));
+    }
+
+    Ok(DecodedInstruction {
+        address,
+        opcode,
+        operands,
+    })
+}
+
+// Decodes the program from address 0 in a straight line, stopping at the
+// first Halt or when the operands run out. Cells that aren't an opcode are
+// treated as data and skipped.
+pub fn decode_all(program: &[i64]) -> Vec<DecodedInstruction> {
+    let mut result = vec![];
+    let mut address = 0;
+
+    while address < program.len() {
+        match decode_at(program, address) {
+            Ok(inst) => {
+                address += 1 + inst.operands.len();
+                let halt = inst.opcode == Opcode::Halt;
+                result.push(inst);
+                if halt {
+                    break;
+                }
+            }
+            Err(VmError::MissingOperands { .. }) => break,
+            Err(_) => address += 1,
+        }
+    }
+
+    result
+}
+
+// Like decode_all but stops at the first cell that can't be decoded, for
+// callers that need the code to be contiguous.
+pub(super) fn decode_straight(program: &[i64]) -> Vec<DecodedInstruction> {
+    let mut result = vec![];
+    let mut address = 0;
+
+    while let Ok(inst) = decode_at(program, address) {
+        address += 1 + inst.operands.len();
+        let halt = inst.opcode == Opcode::Halt;
+        result.push(inst);
+        if halt {
+            break;
+        }
+    }
+
+    result
+}
+
+fn mnemonic(opcode: Opcode) -> &'static str {
+    match opcode {
+        Opcode::Add => "ADD",
+        Opcode::Multiply => "MUL",
+        Opcode::Input => "IN",
+        Opcode::Output => "OUT",
+        Opcode::JumpIfTrue => "JT",
+        Opcode::JumpIfFalse => "JF",
+        Opcode::LessThan => "LT",
+        Opcode::Equals => "EQ",
+        Opcode::AdjustRelativeBase => "ARB",
+        Opcode::Halt => "HLT",
+    }
+}
+
+// One line per instruction of the straight-line decode, e.g.
+// "0004: MUL @16, #10, @16". Operands are prefixed with @ for position, #
+// for immediate and ~ for relative mode.
+pub fn disassemble(bytecode: &[i64]) -> Vec<String> {
+    disassemble_lines(bytecode, false)
+}
+
+// Same as disassemble but position operands that point into the program
+// also show the value stored there, e.g. "@16=7".
+pub fn disassemble_annotated(bytecode: &[i64]) -> Vec<String> {
+    disassemble_lines(bytecode, true)
+}
+
+fn disassemble_lines(bytecode: &[i64], annotate: bool) -> Vec<String> {
+    decode_all(bytecode)
+        .iter()
+        .map(|inst| {
+            let operands: Vec<String> = inst
+                .operands
+                .iter()
+                .map(|op| match op.mode {
+                    Mode::Position => {
+                        let value = usize::try_from(op.value)
+                            .ok()
+                            .and_then(|a| bytecode.get(a))
+                            .filter(|_| annotate);
+                        match value {
+                            Some(v) => format!("@{}={}", op.value, v),
+                            None => format!("@{}", op.value),
+                        }
+                    }
+                    Mode::Immediate => format!("#{}", op.value),
+                    Mode::Relative => format!("~{}", op.value),
+                })
+                .collect();
+
+            if operands.is_empty() {
+                format!("{:04}: {}", inst.address, mnemonic(inst.opcode))
+            } else {
+                format!(
+                    "{:04}: {} {}",
+                    inst.address,
+                    mnemonic(inst.opcode),
+                    operands.join(", ")
+                )
+            }
+        })
+        .collect()
+}
+
+impl VM {
+    // Disassembles the current memory, including any self-modifications.
+    pub fn disassemble(&self) -> Vec<String> {
+        disassemble(&self.bytecode)
+    }
+}
+
+// Control flow edges (jump address, target) of every conditional jump in the
+// straight-line decode. The target is None when it's only known at run time,
+// i.e. read in position or relative mode, or when it's a negative constant.
+pub fn jump_graph(program: &[i64]) -> Vec<(usize, Option<usize>)> {
+    decode_all(program)
+        .iter()
+        .filter(|inst| inst.opcode == Opcode::JumpIfTrue || inst.opcode == Opcode::JumpIfFalse)
+        .map(|inst| {
+            let target = &inst.operands[1];
+            match target.mode {
+                Mode::Immediate => (inst.address, usize::try_from(target.value).ok()),
+                _ => (inst.address, None),
+            }
+        })
+        .collect()
+}
+
+// Number of instructions of each width (opcode plus operands) in the
+// straight-line decode.
+pub fn instruction_size_histogram(program: &[i64]) -> HashMap<usize, usize> {
+    let mut histogram = HashMap::new();
+    for inst in decode_all(program) {
+        *histogram.entry(inst.operands.len() + 1).or_insert(0) += 1;
+    }
+
+    histogram
+}
+
+// Whether the straight-line decode reaches an Input before a Halt. Jumps
+// aren't followed so this can report true for inputs that are never run.
+pub fn requires_input(program: &[i64]) -> bool {
+    decode_all(program)
+        .iter()
+        .any(|inst| inst.opcode == Opcode::Input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_all() {
+        // 33 at address 4 isn't an opcode so it's skipped as data.
+        let decoded = decode_all(&[1002, 4, 3, 4, 33, 99]);
+        let opcodes: Vec<Opcode> = decoded.iter().map(|d| d.opcode).collect();
+        assert_eq!(opcodes, vec![Opcode::Multiply, Opcode::Halt]);
+        assert_eq!(decoded[0].address, 0);
+        assert_eq!(decoded[1].address, 5);
+        assert_eq!(decoded[0].opcode, Opcode::Multiply);
+
+        let modes: Vec<&Mode> = decoded[0].operands.iter().map(|o| &o.mode).collect();
+        assert_eq!(
+            modes,
+            vec![&Mode::Position, &Mode::Immediate, &Mode::Position]
+        );
+
+        // Stops when the operands run out.
+        assert!(decode_all(&[1002, 4]).is_empty());
+    }
+
+    #[test]
+    fn test_disassemble() {
+        let program = [3, 16, 1002, 16, 10, 16, 204, -1, 99, 7];
+        assert_eq!(
+            disassemble(&program),
+            vec![
+                "0000: IN @16",
+                "0002: MUL @16, #10, @16",
+                "0006: OUT ~-1",
+                "0008: HLT",
+            ]
+        );
+
+        // Skips the 42, which isn't an instruction, like decode_all.
+        assert_eq!(
+            disassemble(&[104, 1, 42, 99]),
+            vec!["0000: OUT #1", "0003: HLT"]
+        );
+
+        // Turns the Halt at 4 into a multiplication.
+        let mut vm = VM::new(vec![1101, 1, 1, 4, 99, 0, 0, 0]);
+        assert_eq!(vm.disassemble()[1], "0004: HLT");
+        vm.step().unwrap();
+        assert_eq!(vm.disassemble()[1], "0004: MUL @0, @0, @0");
+    }
+
+    #[test]
+    fn test_disassemble_annotated() {
+        let program = [3, 16, 1002, 9, 10, 16, 204, -1, 99, 7];
+        assert_eq!(
+            disassemble_annotated(&program),
+            vec![
+                "0000: IN @16",
+                "0002: MUL @9=7, #10, @16",
+                "0006: OUT ~-1",
+                "0008: HLT",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_instruction_size_histogram() {
+        let program = [1101, 1, 1, 0, 1102, 2, 2, 1, 104, 5, 1105, 1, 13, 99, 42];
+        let histogram = instruction_size_histogram(&program);
+        assert_eq!(histogram.len(), 4);
+        assert_eq!(histogram[&4], 2);
+        assert_eq!(histogram[&3], 1);
+        assert_eq!(histogram[&2], 1);
+        assert_eq!(histogram[&1], 1);
+
+        // Skips the 42, which isn't an instruction.
+        let histogram = instruction_size_histogram(&[104, 1, 42, 99]);
+        assert_eq!(histogram.len(), 2);
+        assert_eq!(histogram[&2], 1);
+        assert_eq!(histogram[&1], 1);
+    }
+
+    #[test]
+    fn test_jump_graph() {
+        // Always jumps from 0 to 7, the jump at 3 reads its target from 9.
+        let program = [1105, 1, 7, 105, 1, 9, 99, 104, 1, 99];
+        assert_eq!(jump_graph(&program), vec![(0, Some(7)), (3, None)]);
+        assert!(jump_graph(&[99]).is_empty());
+
+        // Relative targets and negative constants are dynamic too.
+        let program = [2105, 1, 0, 1106, 0, -1, 99];
+        assert_eq!(jump_graph(&program), vec![(0, None), (3, None)]);
+    }
+
+    #[test]
+    fn test_requires_input() {
+        assert!(requires_input(&[3, 0, 4, 0, 99]));
+
+        let quine = [
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        assert!(!requires_input(&quine));
+    }
+
+    #[test]
+    fn test_decode_at() {
+        let program = [1002, 4, 3, 4, 33, 1101, 1];
+
+        let decoded = decode_at(&program, 0).unwrap();
+        assert_eq!(decoded.opcode, Opcode::Multiply);
+        assert_eq!(decoded.operands[1].value, 3);
+
+        assert_eq!(
+            decode_at(&program, 4).unwrap_err(),
+            VmError::InvalidOpcode { pc: 4, value: 33 }
+        );
+        assert_eq!(
+            decode_at(&program, 5).unwrap_err(),
+            VmError::MissingOperands { pc: 5 }
+        );
+        assert_eq!(
+            decode_at(&[301, 0, 0, 0], 0).unwrap_err(),
+            VmError::InvalidMode { pc: 0, value: 301 }
+        );
+    }
+}
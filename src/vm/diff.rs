@@ -0,0 +1,62 @@
+// Comparing the memory of two VMs.
+
+use super::VM;
+
+// Every address where the memory of a and b differs as (address, a value,
+// b value). Memory past the end of either VM reads as 0.
+pub fn memory_diff(a: &VM, b: &VM) -> Vec<(usize, i64, i64)> {
+    let len = a.bytecode.len().max(b.bytecode.len());
+    (0..len)
+        .map(|i| {
+            let va = a.bytecode.get(i).cloned().unwrap_or(0);
+            let vb = b.bytecode.get(i).cloned().unwrap_or(0);
+            (i, va, vb)
+        })
+        .filter(|&(_, va, vb)| va != vb)
+        .collect()
+}
+
+// Renders memory_diff as a table with one "addr | a | b" row per address.
+pub fn format_memory_diff(a: &VM, b: &VM) -> String {
+    format_diff_rows(&memory_diff(a, b))
+}
+
+pub(crate) fn format_diff_rows(diff: &[(usize, i64, i64)]) -> String {
+    let rows: Vec<(String, String, String)> = diff
+        .iter()
+        .map(|(i, va, vb)| (i.to_string(), va.to_string(), vb.to_string()))
+        .collect();
+
+    let w0 = rows.iter().map(|r| r.0.len()).fold(4, usize::max);
+    let w1 = rows.iter().map(|r| r.1.len()).fold(1, usize::max);
+
+    let mut result = format!("{:>w0$} | {:>w1$} | b\n", "addr", "a", w0 = w0, w1 = w1);
+    for (addr, va, vb) in rows.iter() {
+        result.push_str(&format!(
+            "{:>w0$} | {:>w1$} | {}\n",
+            addr,
+            va,
+            vb,
+            w0 = w0,
+            w1 = w1
+        ));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_memory_diff() {
+        let mut a = VM::new(vec![1, 0, 0, 0, 99]);
+        a.run().unwrap();
+        let b = VM::new(vec![1, 0, 0, 0, 99]);
+
+        assert_eq!(memory_diff(&a, &b), vec![(0, 2, 1)]);
+        assert_eq!(format_memory_diff(&a, &b), "addr | a | b\n   0 | 2 | 1\n");
+        assert_eq!(format_memory_diff(&b, &b), "addr | a | b\n");
+    }
+}
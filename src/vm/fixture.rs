@@ -0,0 +1,59 @@
+// Self-contained regression cases: a csv program on the first line followed
+// by one input per line.
+
+use std::fs;
+
+use crate::InputError;
+
+fn parse_int(line: usize, text: &str) -> Result<i64, InputError> {
+    text.trim().parse::<i64>().map_err(|_| InputError::Parse {
+        line,
+        text: text.to_owned(),
+    })
+}
+
+pub fn load_case(path: &str) -> Result<(Vec<i64>, Vec<i64>), InputError> {
+    let contents = fs::read_to_string(path)?;
+    let mut lines = contents.lines();
+
+    let program = match lines.next() {
+        Some(l) => l
+            .trim()
+            .split(',')
+            .map(|v| parse_int(1, v))
+            .collect::<Result<Vec<i64>, InputError>>()?,
+        None => vec![],
+    };
+
+    let mut inputs = vec![];
+    for (i, l) in lines.enumerate() {
+        if !l.trim().is_empty() {
+            inputs.push(parse_int(i + 2, l)?);
+        }
+    }
+
+    Ok((program, inputs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::VM;
+
+    #[test]
+    fn test_load_case() {
+        let (program, inputs) = load_case("assets/case_add_one").unwrap();
+        assert_eq!(program, vec![3, 9, 1001, 9, 1, 9, 4, 9, 99, 0]);
+        assert_eq!(inputs, vec![41]);
+
+        let mut vm = VM::new(program);
+        vm.set_inputs(&inputs);
+        vm.run().unwrap();
+        assert_eq!(vm.outputs(), vec![42]);
+
+        assert!(matches!(
+            load_case("assets/no_such_case"),
+            Err(InputError::Io(_))
+        ));
+    }
+}
@@ -0,0 +1,111 @@
+// Random programs for fuzzing the VM.
+
+use super::{Mode, Opcode, Operand, ProgramBuilder};
+
+// Cells after the Halt that the generated instructions write to.
+const DATA_CELLS: usize = 8;
+
+// xorshift64, good enough for fuzzing and keeps runs reproducible.
+struct XorShift(u64);
+
+impl XorShift {
+    fn next(&mut self) -> u64 {
+        let mut x = self.0;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.0 = x;
+        x
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+// Generates len random instructions followed by a Halt and a small data
+// section. Writes only go to the data section and jumps only go forward to
+// an instruction boundary, so every program decodes fully and halts. Input
+// and AdjustRelativeBase aren't generated, which keeps relative mode
+// addresses equal to position mode ones.
+pub fn random_program(len: usize, seed: u64) -> Vec<i64> {
+    // xorshift gets stuck on 0.
+    let mut rng = XorShift(seed.max(1));
+
+    let opcodes = [
+        Opcode::Add,
+        Opcode::Multiply,
+        Opcode::Output,
+        Opcode::JumpIfTrue,
+        Opcode::JumpIfFalse,
+        Opcode::LessThan,
+        Opcode::Equals,
+    ];
+    let kinds: Vec<Opcode> = (0..len)
+        .map(|_| opcodes[rng.below(opcodes.len())])
+        .collect();
+
+    // Address of every instruction plus the final Halt.
+    let mut addresses = vec![0];
+    for op in kinds.iter() {
+        addresses.push(addresses.last().unwrap() + 1 + op.operand_count());
+    }
+    let data_start = *addresses.last().unwrap() as i64 + 1;
+    let size = data_start + DATA_CELLS as i64;
+
+    let mut builder = ProgramBuilder::new();
+    for (i, &op) in kinds.iter().enumerate() {
+        let mut read = || match rng.below(3) {
+            0 => Operand::new(rng.below(size as usize) as i64, Mode::Position),
+            1 => Operand::new(rng.next() as i64 % 1000, Mode::Immediate),
+            _ => Operand::new(rng.below(size as usize) as i64, Mode::Relative),
+        };
+        let a = read();
+        let b = read();
+
+        let dest_offset = data_start + rng.below(DATA_CELLS) as i64;
+        let dest = if rng.below(2) == 0 {
+            Operand::new(dest_offset, Mode::Position)
+        } else {
+            Operand::new(dest_offset, Mode::Relative)
+        };
+        let target = addresses[i + 1 + rng.below(len - i)] as i64;
+
+        builder = match op {
+            Opcode::Add => builder.add(a, b, dest),
+            Opcode::Multiply => builder.multiply(a, b, dest),
+            Opcode::LessThan => builder.less_than(a, b, dest),
+            Opcode::Equals => builder.equals(a, b, dest),
+            Opcode::Output => builder.output(a),
+            Opcode::JumpIfTrue => builder.jump_if_true(a, Operand::new(target, Mode::Immediate)),
+            Opcode::JumpIfFalse => builder.jump_if_false(a, Operand::new(target, Mode::Immediate)),
+            Opcode::Input | Opcode::AdjustRelativeBase | Opcode::Halt => unreachable!(),
+        };
+    }
+
+    let data: Vec<i64> = (0..DATA_CELLS).map(|_| rng.below(100) as i64).collect();
+    builder.halt().data(&data).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::decode::decode_straight;
+    use crate::vm::{RunState, VM};
+
+    #[test]
+    fn test_random_program() {
+        for seed in 0..200 {
+            let program = random_program(20, seed);
+            let decoded = decode_straight(&program);
+            assert_eq!(decoded.len(), 21, "seed {}", seed);
+            assert_eq!(decoded[20].opcode, Opcode::Halt, "seed {}", seed);
+
+            let mut vm = VM::new(program);
+            assert_eq!(vm.step_n(100), Ok(RunState::Halted), "seed {}", seed);
+        }
+
+        assert_eq!(random_program(10, 7), random_program(10, 7));
+        assert_eq!(random_program(0, 7)[0], 99);
+    }
+}
@@ -0,0 +1,150 @@
+// A network of Intcode computers that talk in (destination, x, y) packets
+// (day 23).
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use super::{StepResult, VmError, VM};
+
+pub type Packet = (i64, i64, i64);
+
+#[derive(Debug, Default)]
+pub struct Network {
+    vms: BTreeMap<i64, VM>,
+    // Outputs of each VM that don't form a full packet yet.
+    pending: BTreeMap<i64, Vec<i64>>,
+    // Packets sent to addresses that aren't part of the network.
+    undelivered: Vec<Packet>,
+    // VMs that ended their last turn waiting for input.
+    idle: BTreeSet<i64>,
+}
+
+impl Network {
+    pub fn new() -> Network {
+        Network::default()
+    }
+
+    // Adds a VM to the network. Its first input is its own address.
+    pub fn add(&mut self, id: i64, mut vm: VM) {
+        vm.set_inputs(&[id]);
+        self.vms.insert(id, vm);
+        self.pending.insert(id, vec![]);
+    }
+
+    pub fn vm(&self, id: i64) -> Option<&VM> {
+        self.vms.get(&id)
+    }
+
+    pub fn undelivered(&self) -> &[Packet] {
+        &self.undelivered
+    }
+
+    // Runs every VM in address order until it halts or asks for input with
+    // an empty queue. Packets are routed as soon as a VM finishes its turn.
+    // A VM that ended its last turn waiting is given -1 at the start of its
+    // next turn, unless a packet arrived for it in the meantime. Returns all
+    // the packets sent during the tick.
+    pub fn tick(&mut self) -> Result<Vec<Packet>, VmError> {
+        let mut sent = vec![];
+        let ids: Vec<i64> = self.vms.keys().cloned().collect();
+
+        for id in ids {
+            let mut packets = vec![];
+            {
+                let vm = self.vms.get_mut(&id).unwrap();
+                let pending = self.pending.get_mut(&id).unwrap();
+                if self.idle.remove(&id) && vm.inputs.is_empty() {
+                    vm.set_inputs(&[-1]);
+                }
+                loop {
+                    match vm.step()? {
+                        StepResult::Continued => (),
+                        StepResult::Halted => break,
+                        StepResult::WaitingForInput => {
+                            self.idle.insert(id);
+                            break;
+                        }
+                        StepResult::Output(v) => {
+                            pending.push(v);
+                            if pending.len() == 3 {
+                                packets.push((pending[0], pending[1], pending[2]));
+                                pending.clear();
+                            }
+                        }
+                    }
+                }
+            }
+
+            for &p in packets.iter() {
+                self.deliver(p);
+            }
+            sent.extend(packets);
+        }
+
+        Ok(sent)
+    }
+
+    fn deliver(&mut self, packet: Packet) {
+        let (dest, x, y) = packet;
+        match self.vms.get_mut(&dest) {
+            Some(vm) => vm.set_inputs(&[x, y]),
+            None => self.undelivered.push(packet),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_network() {
+        let mut network = Network::new();
+
+        // Reads its address and sends (1, 5, 7).
+        network.add(0, VM::new(vec![3, 100, 104, 1, 104, 5, 104, 7, 99]));
+
+        // Reads its address and a packet, then sends (255, x + y, 0).
+        network.add(
+            1,
+            VM::new(vec![
+                3, 100, 3, 101, 3, 102, 1, 101, 102, 103, 104, 255, 4, 103, 104, 0, 99,
+            ]),
+        );
+
+        let sent = network.tick().unwrap();
+        assert_eq!(sent, vec![(1, 5, 7), (255, 12, 0)]);
+        assert_eq!(network.undelivered(), &[(255, 12, 0)]);
+    }
+
+    #[test]
+    fn test_empty_queue_reads_minus_one() {
+        let mut network = Network::new();
+
+        // Reads its address and then one more value which it echoes back
+        // to address 255.
+        network.add(
+            0,
+            VM::new(vec![3, 100, 3, 101, 104, 255, 4, 101, 104, 0, 99]),
+        );
+
+        assert!(network.tick().unwrap().is_empty());
+        assert_eq!(network.tick().unwrap(), vec![(255, -1, 0)]);
+    }
+
+    #[test]
+    fn test_packet_before_minus_one() {
+        let mut network = Network::new();
+
+        // Same echo program as above, at address 0.
+        network.add(
+            0,
+            VM::new(vec![3, 100, 3, 101, 104, 255, 4, 101, 104, 0, 99]),
+        );
+
+        // Sends (0, 5, 7) after 0 already waited for input in this tick.
+        network.add(1, VM::new(vec![3, 100, 104, 0, 104, 5, 104, 7, 99]));
+
+        assert_eq!(network.tick().unwrap(), vec![(0, 5, 7)]);
+        assert_eq!(network.tick().unwrap(), vec![(255, 5, 0)]);
+    }
+}
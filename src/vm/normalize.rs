@@ -0,0 +1,83 @@
+// Canonicalizes operand modes of straight-line programs so that programs
+// that only differ in how they spell constants compare equal.
+
+use std::collections::BTreeSet;
+
+use super::decode::decode_straight;
+use super::{Mode, Opcode};
+
+// Rewrites position mode reads of cells that never change into immediate
+// reads of the value held there. Only applies to programs that decode in a
+// straight line up to a Halt without jumps or relative writes, since only
+// then every write target is known. A read qualifies if its address lies
+// past the decoded code and no instruction writes to it. Anything else is
+// returned unchanged, which also makes the transform idempotent.
+pub fn normalize_addresses(program: &[i64]) -> Vec<i64> {
+    let mut result = program.to_vec();
+    let decoded = decode_straight(program);
+    match decoded.last() {
+        Some(inst) if inst.opcode == Opcode::Halt => (),
+        _ => return result,
+    }
+
+    let code_end = decoded.last().map_or(0, |inst| inst.address + 1);
+    let mut written = BTreeSet::new();
+    for inst in decoded.iter() {
+        let target = match inst.opcode {
+            Opcode::JumpIfTrue | Opcode::JumpIfFalse => return result,
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => &inst.operands[2],
+            Opcode::Input => &inst.operands[0],
+            _ => continue,
+        };
+        if target.mode == Mode::Relative {
+            return result;
+        }
+        written.insert(target.value);
+    }
+
+    for inst in decoded.iter() {
+        let reads = match inst.opcode {
+            Opcode::Add | Opcode::Multiply | Opcode::LessThan | Opcode::Equals => 2,
+            Opcode::Output | Opcode::AdjustRelativeBase => 1,
+            _ => 0,
+        };
+
+        for (i, op) in inst.operands.iter().take(reads).enumerate() {
+            if op.mode != Mode::Position || written.contains(&op.value) {
+                continue;
+            }
+            let address = op.value as usize;
+            if op.value < 0 || address < code_end || address >= program.len() {
+                continue;
+            }
+
+            result[inst.address] += 10_i64.pow(i as u32 + 2);
+            result[inst.address + 1 + i] = program[address];
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::VM;
+
+    #[test]
+    fn test_normalize_addresses() {
+        // Adds the constants at 9 and 10 into 11 and outputs it.
+        let program = vec![1, 9, 10, 11, 4, 11, 99, 0, 0, 5, 7, 0];
+        let normalized = normalize_addresses(&program);
+        assert_eq!(normalized, vec![1101, 5, 7, 11, 4, 11, 99, 0, 0, 5, 7, 0]);
+        assert_eq!(normalize_addresses(&normalized), normalized);
+
+        let mut vm = VM::new(normalized);
+        vm.run().unwrap();
+        assert_eq!(vm.outputs(), vec![12]);
+
+        // Jumps make the write targets unknowable so nothing changes.
+        let program = vec![1105, 1, 3, 4, 5, 99];
+        assert_eq!(normalize_addresses(&program), program);
+    }
+}
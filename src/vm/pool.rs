@@ -0,0 +1,85 @@
+// Deduplicated VM states for searches that branch on VM snapshots.
+
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use super::VmState;
+
+// Stores snapshots under a key computed by a user provided function, so that
+// states the search considers equivalent are only explored once.
+pub struct StatePool<K, F> {
+    key: F,
+    states: HashMap<K, VmState>,
+}
+
+impl<K, F> StatePool<K, F>
+where
+    K: Hash + Eq,
+    F: Fn(&VmState) -> K,
+{
+    pub fn new(key: F) -> Self {
+        StatePool {
+            key,
+            states: HashMap::new(),
+        }
+    }
+
+    // Adds the state unless an equivalent one is already pooled. Returns
+    // whether it was added.
+    pub fn insert(&mut self, state: VmState) -> bool {
+        let k = (self.key)(&state);
+        if self.states.contains_key(&k) {
+            return false;
+        }
+
+        self.states.insert(k, state);
+        true
+    }
+
+    pub fn get(&self, key: &K) -> Option<&VmState> {
+        self.states.get(key)
+    }
+
+    // Whether an equivalent state is already pooled.
+    pub fn contains(&self, state: &VmState) -> bool {
+        self.states.contains_key(&(self.key)(state))
+    }
+
+    pub fn len(&self) -> usize {
+        self.states.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.states.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::VM;
+
+    #[test]
+    fn test_state_pool() {
+        // Outputs its input and halts.
+        let program = vec![3, 0, 4, 0, 99];
+        let mut pool = StatePool::new(|s: &VmState| s.outputs.clone());
+        assert!(pool.is_empty());
+
+        for &input in [7, 8, 7].iter() {
+            let mut vm = VM::new(program.clone()).with_input(input);
+            vm.run().unwrap();
+            pool.insert(vm.snapshot());
+        }
+        assert_eq!(pool.len(), 2);
+
+        let state = pool.get(&vec![8]).unwrap().clone();
+        assert!(state.done);
+        assert!(pool.contains(&state));
+        assert!(!pool.insert(state));
+
+        let mut vm = VM::new(program);
+        vm.restore(pool.get(&vec![7]).unwrap().clone());
+        assert_eq!(vm.outputs(), vec![7]);
+    }
+}
@@ -0,0 +1,185 @@
+// Assertions that are handy when writing tests against Intcode programs.
+
+use super::diff::format_diff_rows;
+use super::{chain_output, feedback_output, VM};
+
+// Runs the program to completion and panics if it produced any output.
+// Useful for programs like day 2 whose answer lives in memory.
+pub fn assert_no_output(program: &[i64], inputs: &[i64]) {
+    let mut vm = VM::new(program.to_vec()).with_inputs(inputs);
+    vm.run().unwrap();
+
+    let outputs = vm.outputs_slice();
+    if !outputs.is_empty() {
+        panic!("Expected no output but program produced: {:?}", outputs);
+    }
+}
+
+// Runs the program the given number of times and panics unless every run
+// produced the same output.
+pub fn assert_deterministic(program: &[i64], inputs: &[i64], runs: usize) {
+    let mut expected: Option<Vec<i64>> = None;
+
+    for i in 0..runs {
+        let mut vm = VM::new(program.to_vec()).with_inputs(inputs);
+        vm.run().unwrap();
+        let outputs = vm.outputs();
+
+        match &expected {
+            Some(e) if *e != outputs => panic!(
+                "Run {} produced {:?} but the first run produced {:?}",
+                i, outputs, e
+            ),
+            Some(_) => (),
+            None => expected = Some(outputs),
+        }
+    }
+}
+
+// Panics unless the simple chain and the feedback loop agree on the phases.
+// With part 1 phases every amplifier halts after its single output, so the
+// feedback loop must degrade to the chain.
+pub fn assert_chain_feedback_agree(program: &[i64], phases: &[i64]) {
+    let chain = chain_output(program, phases).expect("chain failed");
+    let feedback = feedback_output(program, phases).expect("feedback loop failed");
+    if chain != feedback {
+        panic!(
+            "Phases {:?}: chain produced {} but feedback loop produced {}",
+            phases, chain, feedback
+        );
+    }
+}
+
+// Runs both programs and panics unless they agree on every cell that either
+// of them changed. Code cells that neither run wrote to may differ.
+pub fn assert_memory_equivalent(a: &[i64], b: &[i64], inputs: &[i64]) {
+    let run = |program: &[i64]| {
+        let mut vm = VM::new(program.to_vec()).with_inputs(inputs);
+        vm.run().unwrap();
+        vm.bytecode()
+    };
+    let cell = |memory: &[i64], i: usize| memory.get(i).cloned().unwrap_or(0);
+
+    let (ma, mb) = (run(a), run(b));
+    let len = ma.len().max(mb.len());
+    let diff: Vec<(usize, i64, i64)> = (0..len)
+        .filter(|&i| cell(&ma, i) != cell(a, i) || cell(&mb, i) != cell(b, i))
+        .map(|i| (i, cell(&ma, i), cell(&mb, i)))
+        .filter(|&(_, va, vb)| va != vb)
+        .collect();
+
+    if !diff.is_empty() {
+        panic!("Memory differs after running:\n{}", format_diff_rows(&diff));
+    }
+}
+
+// Runs the program and panics if it wrote to any cell of the original
+// program. Writes to memory past the end of the program are allowed.
+pub fn assert_pure(program: &[i64], inputs: &[i64]) {
+    let mut vm = VM::new(program.to_vec()).with_inputs(inputs);
+    vm.run().unwrap();
+
+    let diff: Vec<(usize, i64, i64)> = program
+        .iter()
+        .enumerate()
+        .map(|(i, &v)| (i, v, vm.read_mem(i)))
+        .filter(|&(_, before, after)| before != after)
+        .collect();
+
+    if !diff.is_empty() {
+        panic!(
+            "Program modified its own memory:\n{}",
+            format_diff_rows(&diff)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_assert_no_output() {
+        let program = vec![1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+        assert_no_output(&program, &[]);
+    }
+
+    #[test]
+    fn test_assert_deterministic() {
+        let quine = vec![
+            109, 1, 204, -1, 1001, 100, 1, 100, 1008, 100, 16, 101, 1006, 101, 0, 99,
+        ];
+        assert_deterministic(&quine, &[], 5);
+    }
+
+    #[test]
+    fn test_assert_chain_feedback_agree() {
+        let samples: [(&[i64], [i64; 5]); 3] = [
+            (
+                &[
+                    3, 15, 3, 16, 1002, 16, 10, 16, 1, 16, 15, 15, 4, 15, 99, 0, 0,
+                ],
+                [4, 3, 2, 1, 0],
+            ),
+            (
+                &[
+                    3, 23, 3, 24, 1002, 24, 10, 24, 1002, 23, -1, 23, 101, 5, 23, 23, 1, 24, 23,
+                    23, 4, 23, 99, 0, 0,
+                ],
+                [0, 1, 2, 3, 4],
+            ),
+            (
+                &[
+                    3, 31, 3, 32, 1002, 32, 10, 32, 1001, 31, -2, 31, 1007, 31, 0, 33, 1002, 33, 7,
+                    33, 1, 33, 31, 31, 1, 32, 31, 31, 4, 31, 99, 0, 0, 0,
+                ],
+                [1, 0, 4, 3, 2],
+            ),
+        ];
+
+        for (program, phases) in samples.iter() {
+            assert_chain_feedback_agree(program, phases);
+        }
+    }
+
+    #[test]
+    fn test_assert_memory_equivalent() {
+        let program = [1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+        // Same computation with the operands of both instructions swapped.
+        let reordered = [1, 10, 9, 3, 2, 11, 3, 0, 99, 30, 40, 50];
+        assert_memory_equivalent(&program, &reordered, &[]);
+    }
+
+    #[test]
+    #[should_panic(
+        expected = "Memory differs after running:\naddr |    a | b\n   0 | 3500 | 3550\n   3 |   70 | 71\n"
+    )]
+    fn test_assert_memory_equivalent_differs() {
+        let program = [1, 9, 10, 3, 2, 3, 11, 0, 99, 30, 40, 50];
+        // Multiplies by 50 after adding 40 + 31.
+        let other = [1, 9, 10, 3, 2, 3, 11, 0, 99, 31, 40, 50];
+        assert_memory_equivalent(&program, &other, &[]);
+    }
+
+    #[test]
+    fn test_assert_pure() {
+        // Adds 30 and 40 into address 100.
+        let program = [1101, 30, 40, 100, 99];
+        assert_pure(&program, &[]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Program modified its own memory:\naddr | a | b\n   0 | 3 | 7\n")]
+    fn test_assert_pure_self_modifying() {
+        // Reads the input over its own first instruction.
+        let program = [3, 0, 99];
+        assert_pure(&program, &[7]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Expected no output")]
+    fn test_assert_no_output_with_echo() {
+        let program = vec![3, 0, 4, 0, 99];
+        assert_no_output(&program, &[42]);
+    }
+}
@@ -0,0 +1,143 @@
+// Execution traces for comparing two runs of a program.
+
+use std::ops::Range;
+
+use super::{Instruction, Opcode, VM};
+
+// Called with pc and the decoded instruction, see VM::set_trace.
+pub type TraceHook = Box<dyn FnMut(usize, &Instruction) + Send>;
+
+// One executed instruction.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TraceEntry {
+    pub pc: usize,
+    pub opcode: Opcode,
+}
+
+impl VM {
+    // Starts recording every executed instruction.
+    pub fn enable_trace(&mut self) {
+        if self.trace.is_none() {
+            self.trace = Some(vec![]);
+        }
+    }
+
+    // Enables tracing but only records instructions whose pc is in range.
+    pub fn trace_range(&mut self, range: Range<usize>) {
+        self.enable_trace();
+        self.trace_filter = Some(range);
+    }
+
+    // Installs a hook that is called with pc and the decoded instruction
+    // before each instruction executes. Replaces any previous hook.
+    pub fn set_trace(&mut self, f: TraceHook) {
+        self.trace_hook = Some(f);
+    }
+
+    // Instructions executed since tracing was enabled.
+    pub fn trace(&self) -> &[TraceEntry] {
+        self.trace.as_deref().unwrap_or(&[])
+    }
+}
+
+// Index of the first step where the traces differ in pc or opcode. If one
+// trace is a prefix of the other they diverge where the shorter one ends.
+pub fn first_trace_divergence(a: &[TraceEntry], b: &[TraceEntry]) -> Option<usize> {
+    match a.iter().zip(b.iter()).position(|(x, y)| x != y) {
+        Some(i) => Some(i),
+        None if a.len() != b.len() => Some(a.len().min(b.len())),
+        None => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vm::RunState;
+
+    fn run_traced(program: Vec<i64>, input: i64) -> Vec<TraceEntry> {
+        let mut vm = VM::new(program);
+        vm.enable_trace();
+        vm.set_inputs(&[input]);
+        vm.run().unwrap();
+        vm.trace().to_vec()
+    }
+
+    #[test]
+    fn test_first_trace_divergence() {
+        // Reads a value and jumps over the output when it is zero.
+        let program = vec![3, 11, 1006, 11, 7, 104, 1, 99, 0, 0, 0, 0];
+        let a = run_traced(program.clone(), 0);
+        let b = run_traced(program, 5);
+
+        assert_eq!(
+            a,
+            vec![
+                TraceEntry {
+                    pc: 0,
+                    opcode: Opcode::Input
+                },
+                TraceEntry {
+                    pc: 2,
+                    opcode: Opcode::JumpIfFalse
+                },
+                TraceEntry {
+                    pc: 7,
+                    opcode: Opcode::Halt
+                },
+            ]
+        );
+        assert_eq!(first_trace_divergence(&a, &b), Some(2));
+        assert_eq!(first_trace_divergence(&a, &a), None);
+        assert_eq!(first_trace_divergence(&a[..2], &a), Some(2));
+    }
+
+    #[test]
+    fn test_trace_range() {
+        // Counts 3 down to 0 in the loop at 4..11, then outputs and halts.
+        let program = vec![1101, 3, 0, 14, 1001, 14, -1, 14, 1005, 14, 4, 4, 14, 99, 0];
+        let mut vm = VM::new(program);
+        vm.trace_range(4..11);
+        vm.run().unwrap();
+
+        let trace = vm.trace();
+        assert_eq!(trace.len(), 6);
+        assert!(trace.iter().all(|t| (4..11).contains(&t.pc)));
+        assert_eq!(vm.outputs(), vec![0]);
+    }
+
+    #[test]
+    fn test_set_trace() {
+        use std::sync::{Arc, Mutex};
+
+        // Counts 3 down to 0 in the loop at 4..11, then outputs and halts.
+        let program = vec![1101, 3, 0, 14, 1001, 14, -1, 14, 1005, 14, 4, 4, 14, 99, 0];
+        let seen = Arc::new(Mutex::new(vec![]));
+        let sink = seen.clone();
+        let mut vm = VM::new(program);
+        vm.set_trace(Box::new(move |pc, inst| {
+            sink.lock().unwrap().push((pc, inst.opcode))
+        }));
+        vm.run().unwrap();
+
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 9);
+        assert_eq!(seen[0], (0, 1));
+        assert_eq!(seen[1], (4, 1));
+        assert_eq!(seen[8], (13, 99));
+        assert_eq!(seen.iter().filter(|&&(pc, _)| pc == 4).count(), 3);
+    }
+
+    #[test]
+    fn test_trace_waiting_for_input() {
+        let mut vm = VM::new(vec![3, 5, 99, 0, 0, 0]);
+        vm.enable_trace();
+        assert_eq!(vm.resume(), Ok(RunState::WaitingForInput));
+        assert!(vm.trace().is_empty());
+
+        vm.set_inputs(&[1]);
+        assert_eq!(vm.resume(), Ok(RunState::Halted));
+        let pcs: Vec<usize> = vm.trace().iter().map(|t| t.pc).collect();
+        assert_eq!(pcs, vec![0, 2]);
+    }
+}
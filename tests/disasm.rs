@@ -0,0 +1,53 @@
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn disasm(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_disasm"))
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_disasm_file() {
+    let out = disasm(&["assets/disasm_sample"]);
+    assert!(out.contains("MUL"));
+    assert_eq!(out, "0000: MUL @4, #3, @4\n");
+
+    let out = disasm(&["--annotate", "assets/disasm_sample"]);
+    assert_eq!(out, "0000: MUL @4=33, #3, @4=33\n");
+}
+
+#[test]
+fn test_disasm_stdin() {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_disasm"))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(b"104,7,99\n")
+        .unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "0000: OUT #7\n0002: HLT\n"
+    );
+}
+
+#[test]
+fn test_disasm_missing_file() {
+    let status = Command::new(env!("CARGO_BIN_EXE_disasm"))
+        .arg("assets/no_such_file")
+        .stderr(Stdio::null())
+        .status()
+        .unwrap();
+    assert_eq!(status.code(), Some(1));
+}